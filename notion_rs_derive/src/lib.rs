@@ -0,0 +1,200 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr};
+
+/// Property types that can be named in `#[notion(type = "...")]`
+const PROPERTY_TYPES: &[&str] = &[
+    "title",
+    "rich_text",
+    "number",
+    "select",
+    "multi_select",
+    "status",
+    "date",
+    "people",
+    "files",
+    "checkbox",
+    "url",
+    "email",
+    "phone_number",
+    "formula",
+    "relation",
+    "rollup",
+    "created_time",
+    "created_by",
+    "last_edited_time",
+    "last_edited_by",
+    "unique_id",
+    "verification",
+];
+
+/// Options of a `#[notion(...)]` field attribute
+#[derive(Default)]
+struct FieldOptions {
+    name: Option<String>,
+    property_type: Option<String>,
+    database_id: Option<String>,
+    expression: Option<String>,
+    skip: bool,
+}
+
+fn field_options(field: &syn::Field) -> syn::Result<FieldOptions> {
+    let mut options = FieldOptions::default();
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("notion")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                options.skip = true;
+                return Ok(());
+            }
+            let value: LitStr = meta.value()?.parse()?;
+            if meta.path.is_ident("name") {
+                options.name = Some(value.value());
+            } else if meta.path.is_ident("type") {
+                if !PROPERTY_TYPES.contains(&value.value().as_str()) {
+                    return Err(syn::Error::new(
+                        value.span(),
+                        format!("unknown Notion property type \"{}\"", value.value()),
+                    ));
+                }
+                options.property_type = Some(value.value());
+            } else if meta.path.is_ident("database_id") {
+                options.database_id = Some(value.value());
+            } else if meta.path.is_ident("expression") {
+                options.expression = Some(value.value());
+            } else {
+                return Err(meta.error("unsupported notion attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(options)
+}
+
+fn option_tokens(value: &Option<String>) -> TokenStream2 {
+    match value {
+        Some(value) => quote!(::core::option::Option::Some(#value)),
+        None => quote!(::core::option::Option::None),
+    }
+}
+
+/// Derives `notion_rs::record::NotionRecord` for a struct with named fields.
+///
+/// Each field maps to the database property named by `#[notion(name = "...")]`,
+/// or by the field name. The property type is taken from
+/// `#[notion(type = "...")]` or inferred from the field type. Relations take
+/// a `database_id` and formulas an `expression`; `#[notion(skip)]` leaves a
+/// field out and fills it with `Default::default()` when reading. Exactly one
+/// field must have `type = "title"`, since every database has one title.
+#[proc_macro_derive(NotionRecord, attributes(notion))]
+pub fn derive_notion_record(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "NotionRecord requires a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "NotionRecord can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut schema = Vec::new();
+    let mut to_properties = Vec::new();
+    let mut from_properties = Vec::new();
+    let mut title = None;
+
+    for field in fields {
+        let field_ident = field.ident.as_ref().unwrap();
+        let field_type = &field.ty;
+        let options = field_options(field)?;
+        if options.skip {
+            from_properties.push(quote!(#field_ident: ::core::default::Default::default()));
+            continue;
+        }
+
+        match options.property_type.as_deref() {
+            Some("title") if title.is_some() => {
+                return Err(syn::Error::new_spanned(
+                    field_ident,
+                    "a NotionRecord can only have one title field",
+                ))
+            }
+            Some("title") => title = Some(field_ident),
+            Some("relation") if options.database_id.is_none() => {
+                return Err(syn::Error::new_spanned(
+                    field_ident,
+                    "relation fields need the related database: #[notion(database_id = \"...\")]",
+                ))
+            }
+            _ => {}
+        }
+
+        let name = options
+            .name
+            .clone()
+            .unwrap_or_else(|| field_ident.to_string());
+        let property_type = option_tokens(&options.property_type);
+        let database_id = option_tokens(&options.database_id);
+        let expression = option_tokens(&options.expression);
+
+        schema.push(quote! {
+            if let ::core::option::Option::Some(property) =
+                ::notion_rs::record::field_schema::<#field_type>(#property_type, #database_id, #expression)
+            {
+                schema.insert(#name.to_string(), property);
+            }
+        });
+        to_properties.push(quote! {
+            ::notion_rs::record::insert_property(&mut properties, #name, #property_type, &self.#field_ident)?;
+        });
+        from_properties.push(quote! {
+            #field_ident: ::notion_rs::record::read_property::<#field_type>(properties, #name)?
+        });
+    }
+
+    if title.is_none() {
+        return Err(syn::Error::new_spanned(
+            ident,
+            "NotionRecord needs a title field: #[notion(type = \"title\")]",
+        ));
+    }
+
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::notion_rs::record::NotionRecord for #ident #type_generics #where_clause {
+            fn schema() -> ::std::collections::BTreeMap<::std::string::String, ::notion_rs::schema::PropertySchema> {
+                let mut schema = ::std::collections::BTreeMap::new();
+                #(#schema)*
+                schema
+            }
+
+            fn to_properties(&self) -> ::core::result::Result<::notion_rs::record::Value, ::notion_rs::error::NotionError> {
+                let mut properties = ::notion_rs::record::Map::new();
+                #(#to_properties)*
+                ::core::result::Result::Ok(::notion_rs::record::Value::Object(properties))
+            }
+
+            fn from_properties(properties: &::notion_rs::record::Value) -> ::core::result::Result<Self, ::notion_rs::error::NotionError> {
+                ::core::result::Result::Ok(Self {
+                    #(#from_properties),*
+                })
+            }
+        }
+    })
+}
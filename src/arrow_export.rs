@@ -0,0 +1,239 @@
+use arrow::array::{
+    ArrayRef, BooleanBuilder, Float64Builder, ListBuilder, StringArray, StringBuilder,
+};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use futures::stream::{Stream, StreamExt};
+use futures::TryStreamExt;
+use parquet::arrow::ArrowWriter;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::sync::Arc;
+
+use crate::client::NotionClient;
+use crate::database::{Database, DatabaseQuery};
+use crate::error::NotionError;
+use crate::export::{default_columns, flatten_property_value, list_items, CsvExportOptions};
+use crate::page::Page;
+use crate::schema::PropertySchema;
+
+/// Name of the column holding the page ID
+pub const PAGE_ID_COLUMN: &str = "id";
+
+/// Field metadata key holding the name of the property of a column
+pub const PROPERTY_METADATA_KEY: &str = "notion_property";
+
+fn arrow_error(error: impl std::error::Error + Send + Sync + 'static) -> NotionError {
+    NotionError::Io(std::io::Error::new(std::io::ErrorKind::Other, error))
+}
+
+fn timestamp_type() -> DataType {
+    DataType::Timestamp(TimeUnit::Millisecond, Some("+00:00".into()))
+}
+
+fn list_type() -> DataType {
+    DataType::List(Arc::new(Field::new("item", DataType::Utf8, true)))
+}
+
+/// Arrow type of the column for a property
+fn column_type(schema: &PropertySchema) -> DataType {
+    match schema {
+        PropertySchema::Number(_) => DataType::Float64,
+        PropertySchema::Checkbox => DataType::Boolean,
+        PropertySchema::Date | PropertySchema::CreatedTime | PropertySchema::LastEditedTime => {
+            timestamp_type()
+        }
+        PropertySchema::MultiSelect(_)
+        | PropertySchema::People
+        | PropertySchema::Relation(_)
+        | PropertySchema::Files => list_type(),
+        _ => DataType::Utf8,
+    }
+}
+
+/// Properties of a database with the names of their columns, in column
+/// order. A property named like the page ID column gets the column
+/// `property_<name>`, with a numeric suffix if another property has that
+/// name.
+fn property_columns(database: &Database) -> Vec<(String, String)> {
+    let names = default_columns(database);
+    let mut used: HashSet<String> = names.iter().cloned().collect();
+    used.insert(PAGE_ID_COLUMN.to_string());
+    names
+        .into_iter()
+        .map(|name| {
+            if name != PAGE_ID_COLUMN {
+                return (name.clone(), name);
+            }
+            let base = format!("property_{}", name);
+            let mut column = base.clone();
+            let mut suffix = 2;
+            while !used.insert(column.clone()) {
+                column = format!("{}_{}", base, suffix);
+                suffix += 1;
+            }
+            (name, column)
+        })
+        .collect()
+}
+
+/// Arrow schema for the pages of a database: the page ID followed by one
+/// column per property, title first. Each property column names its
+/// property in the `PROPERTY_METADATA_KEY` metadata, since a property named
+/// like the page ID column is renamed.
+///
+/// Numbers map to `Float64`, checkboxes to `Boolean`, dates and timestamps to
+/// `Timestamp(Millisecond, +00:00)` (the start of date ranges), multi-selects,
+/// people, relations and files to `List<Utf8>`, and everything else to `Utf8`.
+pub fn database_arrow_schema(database: &Database) -> SchemaRef {
+    let mut fields = vec![Field::new(PAGE_ID_COLUMN, DataType::Utf8, false)];
+    fields.extend(
+        property_columns(database)
+            .into_iter()
+            .map(|(name, column)| {
+                let property = &database.properties[&name];
+                Field::new(column, column_type(&property.schema), true)
+                    .with_metadata(HashMap::from([(PROPERTY_METADATA_KEY.to_string(), name)]))
+            }),
+    );
+    Arc::new(Schema::new(fields))
+}
+
+fn date_start(value: &Value) -> Option<&str> {
+    let kind = value["type"].as_str()?;
+    match kind {
+        "date" => value["date"]["start"].as_str(),
+        _ => value[kind].as_str(),
+    }
+}
+
+/// Converts pages of a database into a record batch with the given schema.
+/// Columns are filled from the property named in their metadata, or else
+/// from the property with the name of the column.
+pub fn pages_to_record_batch(
+    schema: SchemaRef,
+    pages: &[Page],
+) -> Result<RecordBatch, NotionError> {
+    let text_options = CsvExportOptions::default();
+    let no_titles = HashMap::new();
+
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            let name = match field.metadata().get(PROPERTY_METADATA_KEY) {
+                Some(property) => property.as_str(),
+                None if field.name() == PAGE_ID_COLUMN => {
+                    let ids: StringArray =
+                        pages.iter().map(|page| Some(page.id.as_str())).collect();
+                    return Ok(Arc::new(ids) as ArrayRef);
+                }
+                None => field.name().as_str(),
+            };
+            let values = pages.iter().map(|page| &page.properties[name]);
+            let array: ArrayRef = match field.data_type() {
+                DataType::Float64 => {
+                    let mut builder = Float64Builder::with_capacity(pages.len());
+                    values.for_each(|value| builder.append_option(value["number"].as_f64()));
+                    Arc::new(builder.finish())
+                }
+                DataType::Boolean => {
+                    let mut builder = BooleanBuilder::with_capacity(pages.len());
+                    values.for_each(|value| builder.append_option(value["checkbox"].as_bool()));
+                    Arc::new(builder.finish())
+                }
+                DataType::Timestamp(_, _) => {
+                    let dates: StringArray = values.map(date_start).collect();
+                    cast(&dates, field.data_type()).map_err(arrow_error)?
+                }
+                DataType::List(_) => {
+                    let mut builder = ListBuilder::new(StringBuilder::new());
+                    for value in values {
+                        match list_items(value) {
+                            Some(items) => {
+                                items
+                                    .iter()
+                                    .for_each(|item| builder.values().append_value(item));
+                                builder.append(true);
+                            }
+                            None => builder.append(false),
+                        }
+                    }
+                    Arc::new(builder.finish())
+                }
+                _ => {
+                    let texts: StringArray = values
+                        .map(|value| {
+                            (!value.is_null())
+                                .then(|| flatten_property_value(value, &text_options, &no_titles))
+                        })
+                        .collect();
+                    Arc::new(texts)
+                }
+            };
+            Ok(array)
+        })
+        .collect::<Result<Vec<_>, NotionError>>()?;
+
+    RecordBatch::try_new(schema, columns).map_err(arrow_error)
+}
+
+impl NotionClient {
+    /// Queries a database and yields the results as record batches of up to
+    /// `batch_size` pages, following pagination lazily. Relation and people
+    /// values are completed beyond the items included in page objects.
+    pub async fn query_database_record_batches<'a>(
+        &'a self,
+        database_id: &'a str,
+        query: DatabaseQuery,
+        batch_size: usize,
+    ) -> Result<
+        (
+            SchemaRef,
+            impl Stream<Item = Result<RecordBatch, NotionError>> + 'a,
+        ),
+        NotionError,
+    > {
+        let database = self.get_database(database_id).await?.data;
+        let schema = database_arrow_schema(&database);
+        let batch_schema = schema.clone();
+        let batches = self
+            .query_database_stream(database_id, query)
+            .chunks(batch_size.max(1))
+            .then(move |pages| {
+                let schema = batch_schema.clone();
+                async move {
+                    let mut pages = pages.into_iter().collect::<Result<Vec<_>, _>>()?;
+                    for page in &mut pages {
+                        self.complete_list_properties(page).await?;
+                    }
+                    pages_to_record_batch(schema, &pages)
+                }
+            });
+        Ok((schema, batches))
+    }
+
+    /// Writes every page of a database matching the query to a Parquet file.
+    /// Returns the number of rows written.
+    pub async fn write_database_parquet<W: Write + Send>(
+        &self,
+        database_id: &str,
+        query: DatabaseQuery,
+        writer: W,
+    ) -> Result<usize, NotionError> {
+        let (schema, batches) = self
+            .query_database_record_batches(database_id, query, 1000)
+            .await?;
+        futures::pin_mut!(batches);
+        let mut parquet = ArrowWriter::try_new(writer, schema, None).map_err(arrow_error)?;
+        let mut rows = 0;
+        while let Some(batch) = batches.try_next().await? {
+            rows += batch.num_rows();
+            parquet.write(&batch).map_err(arrow_error)?;
+        }
+        parquet.close().map_err(arrow_error)?;
+        Ok(rows)
+    }
+}
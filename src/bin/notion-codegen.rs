@@ -0,0 +1,36 @@
+//! Prints a Rust struct matching the schema of a Notion database.
+//!
+//! Usage: `notion-codegen <database_id> [StructName] > src/models/tasks.rs`,
+//! with the integration token in `NOTION_API_TOKEN`.
+
+use notion_rs::codegen::{generate_record, CodegenOptions};
+use notion_rs::NotionClient;
+use std::process::ExitCode;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let Some(database_id) = args.next() else {
+        eprintln!("usage: notion-codegen <database_id> [StructName]");
+        return ExitCode::from(2);
+    };
+    let options = CodegenOptions {
+        struct_name: args.next(),
+    };
+    let Ok(token) = std::env::var("NOTION_API_TOKEN") else {
+        eprintln!("NOTION_API_TOKEN must be set");
+        return ExitCode::from(2);
+    };
+
+    let client = NotionClient::new(&token);
+    match client.get_database(&database_id).await {
+        Ok(database) => {
+            print!("{}", generate_record(&database.data, &options));
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("Failed to fetch database {}: {}", database_id, error);
+            ExitCode::FAILURE
+        }
+    }
+}
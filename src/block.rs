@@ -0,0 +1,244 @@
+use futures::future::{BoxFuture, FutureExt};
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::client::NotionClient;
+use crate::copy::{CopyValues, SameWorkspace};
+use crate::database::MAX_PAGE_SIZE;
+use crate::error::NotionError;
+use crate::page::MAX_CHILDREN;
+use crate::request::RequestBuilder;
+use crate::response::ListResponse;
+
+/// Block object returned by the Notion API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Block {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub block_type: String,
+    #[serde(default)]
+    pub has_children: bool,
+    /// Type-specific content and the remaining block fields
+    #[serde(flatten)]
+    pub content: Value,
+}
+
+impl Block {
+    pub fn children_request(block_id: &str, start_cursor: Option<&str>) -> RequestBuilder {
+        let request = RequestBuilder::new(&format!("/blocks/{}/children", block_id))
+            .query_param("page_size", MAX_PAGE_SIZE);
+        match start_cursor {
+            Some(cursor) => request.query_param("start_cursor", cursor),
+            None => request,
+        }
+    }
+
+    /// Request appending blocks, in the shape returned by `to_request_value`,
+    /// after the existing children of a block or page. At most 100 blocks can
+    /// be appended at once.
+    pub fn append_children_request(block_id: &str, children: &[Value]) -> RequestBuilder {
+        RequestBuilder::new(&format!("/blocks/{}/children", block_id))
+            .method("PATCH")
+            .json_body(serde_json::json!({ "children": children }))
+    }
+
+    /// Request updating the content of a block, given as
+    /// `{ "<type>": { ... } }` with the fields to change
+    pub fn update_request(block_id: &str, content: Value) -> RequestBuilder {
+        RequestBuilder::new(&format!("/blocks/{}", block_id))
+            .method("PATCH")
+            .json_body(content)
+    }
+
+    /// Whether this block type must be created together with its children
+    pub fn embeds_children(block_type: &str) -> bool {
+        matches!(block_type, "table" | "column_list" | "column")
+    }
+
+    /// The block as sent when creating it, without IDs, timestamps and other
+    /// read-only fields. `None` for blocks the API cannot create, such as
+    /// child pages and databases.
+    ///
+    /// A file hosted by Notion is kept as a `file` object with its URL, which
+    /// the API does not accept when creating a block. It has to be uploaded
+    /// again, or linked by its URL, which expires after an hour.
+    pub fn to_request_value(&self) -> Option<Value> {
+        if matches!(
+            self.block_type.as_str(),
+            "child_page" | "child_database" | "link_preview" | "unsupported"
+        ) {
+            return None;
+        }
+        let mut content = self.content.get(&self.block_type)?.clone();
+        if let Some(file) = content["file"].as_object_mut() {
+            file.remove("expiry_time");
+        }
+        Some(serde_json::json!({
+            "object": "block",
+            "type": self.block_type,
+            self.block_type.clone(): content,
+        }))
+    }
+}
+
+/// A block as sent when creating it, with the blocks to create below it
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockTree {
+    /// Request value of the block, without children
+    pub value: Value,
+    pub children: Vec<BlockTree>,
+}
+
+impl BlockTree {
+    /// Request value of the block, with the children embedded that have to be
+    /// created together with it
+    pub fn request_value(&self) -> Value {
+        let mut value = self.value.clone();
+        let kind = value["type"].as_str().unwrap_or_default().to_string();
+        if Block::embeds_children(&kind) && !self.children.is_empty() {
+            value[&kind]["children"] = self.children.iter().map(Self::request_value).collect();
+        }
+        value
+    }
+}
+
+impl NotionClient {
+    /// Copies the children of a block or page below another block or page,
+    /// recursively, uploading files hosted by Notion again. Returns a map from source block IDs to the IDs of their
+    /// copies; blocks that cannot be created through the API are skipped.
+    pub fn copy_block_children<'a>(
+        &'a self,
+        source_id: &'a str,
+        target_id: &'a str,
+    ) -> BoxFuture<'a, Result<HashMap<String, String>, NotionError>> {
+        async move {
+            let values = SameWorkspace { target: Some(self) };
+            self.copy_block_children_to(source_id, self, target_id, &values)
+                .await
+        }
+        .boxed()
+    }
+
+    /// Copies the children of a block or page below a block or page that
+    /// `target` can access, converting every block with `values`
+    pub(crate) fn copy_block_children_to<'a>(
+        &'a self,
+        source_id: &'a str,
+        target: &'a NotionClient,
+        target_id: &'a str,
+        values: &'a dyn CopyValues,
+    ) -> BoxFuture<'a, Result<HashMap<String, String>, NotionError>> {
+        async move {
+            let mut sources = Vec::new();
+            let blocks = self
+                .read_block_tree(source_id, values, &mut sources)
+                .await?;
+            let copies = target.append_block_tree(target_id, &blocks).await?;
+            Ok(sources.into_iter().zip(copies).collect())
+        }
+        .boxed()
+    }
+
+    /// Reads the children of a block or page with all their descendants,
+    /// converting every block with `values` and leaving out the ones it
+    /// skips. The IDs of the blocks kept are added to `ids`, depth first.
+    pub(crate) fn read_block_tree<'a>(
+        &'a self,
+        block_id: &'a str,
+        values: &'a dyn CopyValues,
+        ids: &'a mut Vec<String>,
+    ) -> BoxFuture<'a, Result<Vec<BlockTree>, NotionError>> {
+        async move {
+            let blocks: Vec<Block> = self.list_children_stream(block_id).try_collect().await?;
+            let mut tree = Vec::new();
+            for block in blocks {
+                let Some(value) = values.block(&block).await? else {
+                    continue;
+                };
+                ids.push(block.id.clone());
+                let children = if block.has_children {
+                    self.read_block_tree(&block.id, values, ids).await?
+                } else {
+                    Vec::new()
+                };
+                tree.push(BlockTree { value, children });
+            }
+            Ok(tree)
+        }
+        .boxed()
+    }
+
+    /// Appends blocks with all their descendants below a block or page.
+    /// Returns the IDs of the created blocks, depth first.
+    pub(crate) fn append_block_tree<'a>(
+        &'a self,
+        parent_id: &'a str,
+        blocks: &'a [BlockTree],
+    ) -> BoxFuture<'a, Result<Vec<String>, NotionError>> {
+        async move {
+            let mut ids = Vec::new();
+            for chunk in blocks.chunks(MAX_CHILDREN) {
+                let values: Vec<Value> = chunk.iter().map(BlockTree::request_value).collect();
+                let created: ListResponse<Block> = self
+                    .patch(Block::append_children_request(parent_id, &values))
+                    .await?;
+                for (block, copy) in chunk.iter().zip(created.results) {
+                    ids.extend(self.append_descendants(block, copy.id).await?);
+                }
+            }
+            Ok(ids)
+        }
+        .boxed()
+    }
+
+    /// Child pages and databases anywhere in the content of a block or page,
+    /// without descending into them
+    pub fn list_nested_pages<'a>(
+        &'a self,
+        block_id: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<Block>, NotionError>> {
+        async move {
+            let blocks: Vec<Block> = self.list_children_stream(block_id).try_collect().await?;
+            let mut pages = Vec::new();
+            for block in blocks {
+                match block.block_type.as_str() {
+                    "child_page" | "child_database" => pages.push(block),
+                    _ if block.has_children => {
+                        pages.extend(self.list_nested_pages(&block.id).await?)
+                    }
+                    _ => {}
+                }
+            }
+            Ok(pages)
+        }
+        .boxed()
+    }
+
+    /// Records a created block and appends the children that were not
+    /// embedded in its creation request
+    fn append_descendants<'a>(
+        &'a self,
+        block: &'a BlockTree,
+        copy_id: String,
+    ) -> BoxFuture<'a, Result<Vec<String>, NotionError>> {
+        async move {
+            let mut ids = vec![copy_id.clone()];
+            if block.children.is_empty() {
+                return Ok(ids);
+            }
+            if !Block::embeds_children(block.value["type"].as_str().unwrap_or_default()) {
+                ids.extend(self.append_block_tree(&copy_id, &block.children).await?);
+                return Ok(ids);
+            }
+            // Embedded children were created with the block; pair them up by position
+            let copies: Vec<Block> = self.list_children_stream(&copy_id).try_collect().await?;
+            for (child, copy) in block.children.iter().zip(copies) {
+                ids.extend(self.append_descendants(child, copy.id).await?);
+            }
+            Ok(ids)
+        }
+        .boxed()
+    }
+}
@@ -0,0 +1,175 @@
+use futures::stream::{self, StreamExt, TryStreamExt};
+use serde_json::Value;
+use std::future::Future;
+
+use crate::client::NotionClient;
+use crate::database::DatabaseQuery;
+use crate::error::NotionError;
+use crate::export::page_title;
+use crate::page::Page;
+use crate::request::RequestBuilder;
+use crate::response::ObjectResponse;
+
+/// Options for `NotionClient::bulk_update` and `NotionClient::bulk_archive`
+#[derive(Debug, Clone)]
+pub struct BulkOptions {
+    /// Maximum number of requests in flight at once. Requests still wait for
+    /// the client's rate limiter, if any.
+    pub concurrency: usize,
+    /// Only list the matching pages without changing them
+    pub dry_run: bool,
+}
+
+impl Default for BulkOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 3,
+            dry_run: false,
+        }
+    }
+}
+
+/// Outcome for one page of a bulk operation
+#[derive(Debug)]
+pub struct BulkPageResult {
+    pub page_id: String,
+    /// Plain text title of the page when it was matched
+    pub title: String,
+    /// `Ok` when the page was changed, or would be on a dry run
+    pub result: Result<(), NotionError>,
+}
+
+/// Per-page report of a bulk operation, in query order
+#[derive(Debug, Default)]
+pub struct BulkReport {
+    /// Whether this was a dry run, in which case nothing was changed
+    pub dry_run: bool,
+    pub pages: Vec<BulkPageResult>,
+}
+
+impl BulkReport {
+    /// Pages that were changed successfully
+    pub fn succeeded(&self) -> impl Iterator<Item = &BulkPageResult> {
+        self.pages.iter().filter(|page| page.result.is_ok())
+    }
+
+    /// Pages whose request failed
+    pub fn failed(&self) -> impl Iterator<Item = &BulkPageResult> {
+        self.pages.iter().filter(|page| page.result.is_err())
+    }
+}
+
+/// Change made to every matching page
+#[derive(Debug, Clone)]
+pub(crate) enum BulkAction {
+    /// Set property values, in the shape of the `properties` of a page update
+    Update(Value),
+    Archive,
+}
+
+impl BulkAction {
+    pub(crate) fn request(&self, page_id: &str) -> RequestBuilder {
+        match self {
+            BulkAction::Update(patch) => Page::update_properties_request(page_id, patch.clone()),
+            BulkAction::Archive => Page::archive_request(page_id, true),
+        }
+    }
+}
+
+/// Applies `action` to every page with `send`, at most
+/// `options.concurrency` at a time, or only lists the pages on a dry run
+pub(crate) async fn apply_to_pages<F, Fut>(
+    pages: &[Page],
+    options: &BulkOptions,
+    action: &BulkAction,
+    send: F,
+) -> BulkReport
+where
+    F: Fn(RequestBuilder) -> Fut,
+    Fut: Future<Output = Result<(), NotionError>>,
+{
+    let matched = pages.iter().map(|page| (page.id.clone(), page_title(page)));
+
+    if options.dry_run {
+        return BulkReport {
+            dry_run: true,
+            pages: matched
+                .map(|(page_id, title)| BulkPageResult {
+                    page_id,
+                    title,
+                    result: Ok(()),
+                })
+                .collect(),
+        };
+    }
+
+    let send = &send;
+    let pages = stream::iter(matched)
+        .map(|(page_id, title)| async move {
+            let result = send(action.request(&page_id)).await;
+            BulkPageResult {
+                page_id,
+                title,
+                result,
+            }
+        })
+        .buffered(options.concurrency.max(1))
+        .collect()
+        .await;
+    BulkReport {
+        dry_run: false,
+        pages,
+    }
+}
+
+impl NotionClient {
+    /// Sets the given property values on every page of a database matching
+    /// `filter`. `patch` has the same shape as the `properties` of a page
+    /// update.
+    pub async fn bulk_update(
+        &self,
+        database_id: &str,
+        filter: Value,
+        patch: Value,
+        options: BulkOptions,
+    ) -> Result<BulkReport, NotionError> {
+        self.bulk_apply(database_id, filter, options, BulkAction::Update(patch))
+            .await
+    }
+
+    /// Moves every page of a database matching `filter` to the trash
+    pub async fn bulk_archive(
+        &self,
+        database_id: &str,
+        filter: Value,
+        options: BulkOptions,
+    ) -> Result<BulkReport, NotionError> {
+        self.bulk_apply(database_id, filter, options, BulkAction::Archive)
+            .await
+    }
+
+    /// Sends the PATCH request of `action` for every matching page.
+    ///
+    /// All matching pages are collected before anything is changed, so pages
+    /// that stop matching once patched cannot shift the query cursor.
+    async fn bulk_apply(
+        &self,
+        database_id: &str,
+        filter: Value,
+        options: BulkOptions,
+        action: BulkAction,
+    ) -> Result<BulkReport, NotionError> {
+        let pages: Vec<Page> = self
+            .query_database_stream(database_id, DatabaseQuery::new().filter(filter))
+            .try_collect()
+            .await?;
+        Ok(
+            apply_to_pages(&pages, &options, &action, |request| async move {
+                self.patch::<ObjectResponse<Page>>(request)
+                    .await
+                    .map(|_| ())
+            })
+            .await,
+        )
+    }
+}
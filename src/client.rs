@@ -1,29 +1,40 @@
+use futures::stream::{self, Stream, TryStreamExt};
+use reqwest::multipart::Form;
 use reqwest::Client;
 use serde::de::DeserializeOwned;
 use serde_json::Value;
 use std::time::Duration;
 use tokio::time::sleep;
 
-use crate::database::{Database, DatabaseQuery};
+use crate::block::Block;
+use crate::database::{CreateDatabase, Database, DatabaseQuery, UpdateDatabase, MAX_PAGE_SIZE};
 use crate::error::NotionError;
+use crate::migration::{DesiredSchema, MigrationOptions, MigrationPlan};
+pub use crate::page::Page;
+use crate::page::{CreatePage, Parent, UpdatePage, MAX_CHILDREN};
+use crate::pagination::paginate;
+use crate::rate_limit::RateLimiter;
 use crate::request::RequestBuilder;
-use crate::response::{ListResponse, ObjectResponse, RetryConfig};
-
-/// Page object returned by the Notion API
-#[derive(Debug, serde::Deserialize)]
-pub struct Page {
-    pub id: String,
-    pub parent: serde_json::Value,
-    pub properties: serde_json::Value,
-}
+use crate::response::{ListResponse, ObjectResponse, RetryConfig, User};
+use crate::schema::DatabasePropertyUpdate;
+use crate::search::{SearchObject, SearchQuery, SearchResult};
 
 const NOTION_API_BASE: &str = "https://api.notion.com/v1";
 
+/// Notion API version sent with every request unless another one is set
+/// with `NotionClient::with_api_version`
+pub const DEFAULT_API_VERSION: &str = "2022-06-28";
+
+/// First Notion API version with data sources, needed for `Parent::DataSource`
+pub const DATA_SOURCES_API_VERSION: &str = "2025-09-03";
+
 /// NotionClient handles all communication with the Notion API
 pub struct NotionClient {
     http_client: Client,
     auth_token: String,
+    api_version: String,
     retry_config: RetryConfig,
+    rate_limiter: Option<RateLimiter>,
 }
 
 impl NotionClient {
@@ -32,16 +43,40 @@ impl NotionClient {
         Self {
             http_client: Client::new(),
             auth_token: auth_token.to_string(),
+            api_version: DEFAULT_API_VERSION.to_string(),
             retry_config: RetryConfig::default(),
+            rate_limiter: None,
         }
     }
 
+    /// Set the Notion API version sent with every request, such as
+    /// `DATA_SOURCES_API_VERSION`. Responses follow the shape of that version,
+    /// which this crate models as of `DEFAULT_API_VERSION`.
+    pub fn with_api_version(mut self, version: &str) -> Self {
+        self.api_version = version.to_string();
+        self
+    }
+
     /// Set custom retry configuration
     pub fn with_retry_config(mut self, config: RetryConfig) -> Self {
         self.retry_config = config;
         self
     }
 
+    /// Limit outgoing requests to the given number per second.
+    /// Notion allows an average of three requests per second per integration.
+    pub fn with_rate_limit(mut self, requests_per_second: u32) -> Self {
+        self.rate_limiter = Some(RateLimiter::new(requests_per_second));
+        self
+    }
+
+    /// Wait for the rate limiter, if one is configured
+    async fn throttle(&self) {
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire().await;
+        }
+    }
+
     /// Calculate delay for retry attempt using exponential backoff
     fn calculate_retry_delay(&self, attempt: u32, retry_after: Option<u64>) -> Duration {
         if let Some(seconds) = retry_after {
@@ -61,11 +96,12 @@ impl NotionClient {
         let url = request.build_url(NOTION_API_BASE);
         let mut attempt = 0u32;
         loop {
+            self.throttle().await;
             let response = self
                 .http_client
                 .get(&url)
                 .header("Authorization", format!("Bearer {}", self.auth_token))
-                .header("Notion-Version", "2022-06-28")
+                .header("Notion-Version", &self.api_version)
                 .send()
                 .await?;
 
@@ -118,11 +154,12 @@ impl NotionClient {
         })?;
         let mut attempt = 0u32;
         loop {
+            self.throttle().await;
             let response = self
                 .http_client
                 .post(&url)
                 .header("Authorization", format!("Bearer {}", self.auth_token))
-                .header("Notion-Version", "2022-06-28")
+                .header("Notion-Version", &self.api_version)
                 .json(&body)
                 .send()
                 .await?;
@@ -176,11 +213,12 @@ impl NotionClient {
         })?;
         let mut attempt = 0u32;
         loop {
+            self.throttle().await;
             let response = self
                 .http_client
                 .patch(&url)
                 .header("Authorization", format!("Bearer {}", self.auth_token))
-                .header("Notion-Version", "2022-06-28")
+                .header("Notion-Version", &self.api_version)
                 .json(&body)
                 .send()
                 .await?;
@@ -231,11 +269,73 @@ impl NotionClient {
         let url = request.build_url(NOTION_API_BASE);
         let mut attempt = 0u32;
         loop {
+            self.throttle().await;
             let response = self
                 .http_client
                 .delete(&url)
                 .header("Authorization", format!("Bearer {}", self.auth_token))
-                .header("Notion-Version", "2022-06-28")
+                .header("Notion-Version", &self.api_version)
+                .send()
+                .await?;
+
+            let status = response.status();
+
+            if status.is_success() {
+                return Ok(response.json::<T>().await?);
+            }
+
+            if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                let retry_after = response
+                    .headers()
+                    .get("retry-after")
+                    .and_then(|h| h.to_str().ok())
+                    .and_then(|s| s.parse().ok());
+
+                if attempt < self.retry_config.max_retries {
+                    sleep(self.calculate_retry_delay(attempt, retry_after)).await;
+                    attempt += 1;
+                    continue;
+                }
+                return Err(NotionError::RateLimited {
+                    retry_after: retry_after.unwrap_or(60),
+                });
+            }
+
+            if status == reqwest::StatusCode::UNAUTHORIZED {
+                return Err(NotionError::Unauthorized);
+            }
+
+            let error = response.json::<Value>().await?;
+            return Err(NotionError::ApiError {
+                code: error["code"].as_str().unwrap_or("unknown").to_string(),
+                message: error["message"]
+                    .as_str()
+                    .unwrap_or("Unknown error")
+                    .to_string(),
+            });
+        }
+    }
+
+    /// Performs a multipart POST request, such as sending the content of a
+    /// file upload. The form is built again for every attempt.
+    pub(crate) async fn post_multipart<T>(
+        &self,
+        request: RequestBuilder,
+        form: impl Fn() -> Result<Form, NotionError>,
+    ) -> Result<T, NotionError>
+    where
+        T: DeserializeOwned,
+    {
+        let url = request.build_url(NOTION_API_BASE);
+        let mut attempt = 0u32;
+        loop {
+            self.throttle().await;
+            let response = self
+                .http_client
+                .post(&url)
+                .header("Authorization", format!("Bearer {}", self.auth_token))
+                .header("Notion-Version", &self.api_version)
+                .multipart(form()?)
                 .send()
                 .await?;
 
@@ -277,10 +377,38 @@ impl NotionClient {
         }
     }
 
-    /// Lists all databases shared with the integration
+    /// Downloads a file from a URL outside the API, such as the temporary URL
+    /// of a file hosted by Notion. Returns its content and content type.
+    pub(crate) async fn download(
+        &self,
+        url: &str,
+    ) -> Result<(Vec<u8>, Option<String>), NotionError> {
+        let response = self.http_client.get(url).send().await?.error_for_status()?;
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        Ok((response.bytes().await?.to_vec(), content_type))
+    }
+
+    /// Lists all databases shared with the integration, following every page of results
     pub async fn list_databases(&self) -> Result<ListResponse<Database>, NotionError> {
-        let request = Database::list_request();
-        self.post(request).await
+        let results = self
+            .search_stream(SearchQuery::new().object(SearchObject::Database))
+            .try_filter_map(|result| async move {
+                Ok(match result {
+                    SearchResult::Database(database) => Some(database),
+                    SearchResult::Page(_) => None,
+                })
+            })
+            .try_collect()
+            .await?;
+        Ok(ListResponse {
+            results,
+            has_more: false,
+            next_cursor: None,
+        })
     }
 
     /// Retrieves a database by ID
@@ -303,6 +431,23 @@ impl NotionClient {
         self.post(request).await
     }
 
+    /// Creates a new database with the options set on the builder
+    pub async fn create_database_with(
+        &self,
+        database: CreateDatabase,
+    ) -> Result<ObjectResponse<Database>, NotionError> {
+        self.post(database.request()).await
+    }
+
+    /// Updates title, description, icon, cover, inline display or properties of a database
+    pub async fn update_database_with(
+        &self,
+        database_id: &str,
+        update: UpdateDatabase,
+    ) -> Result<ObjectResponse<Database>, NotionError> {
+        self.patch(update.request(database_id)).await
+    }
+
     /// Updates an existing database
     pub async fn update_database(
         &self,
@@ -314,61 +459,286 @@ impl NotionClient {
         self.patch(request).await
     }
 
-    /// Queries a database with optional filters, sorting, and pagination
+    /// Renames, removes, retypes or updates options of existing properties.
+    /// Properties may be addressed by name or ID; they are resolved against the
+    /// current schema and sent by ID so that concurrent renames are not clobbered.
+    pub async fn update_database_properties(
+        &self,
+        database_id: &str,
+        updates: &[DatabasePropertyUpdate],
+    ) -> Result<ObjectResponse<Database>, NotionError> {
+        let current = self.get_database(database_id).await?;
+        let properties = current.data.resolve_property_updates(updates)?;
+        self.update_database(database_id, None, Some(properties))
+            .await
+    }
+
+    /// Moves a database to the trash
+    pub async fn archive_database(
+        &self,
+        database_id: &str,
+    ) -> Result<ObjectResponse<Database>, NotionError> {
+        let current = self.get_database(database_id).await?;
+        if current.data.is_archived() {
+            return Err(NotionError::AlreadyArchived(database_id.to_string()));
+        }
+        self.patch(Database::archive_request(database_id, true))
+            .await
+    }
+
+    /// Restores a database from the trash
+    pub async fn restore_database(
+        &self,
+        database_id: &str,
+    ) -> Result<ObjectResponse<Database>, NotionError> {
+        let current = self.get_database(database_id).await?;
+        if !current.data.is_archived() {
+            return Err(NotionError::NotArchived(database_id.to_string()));
+        }
+        self.patch(Database::archive_request(database_id, false))
+            .await
+    }
+
+    /// Brings the schema of a database in line with `desired`.
+    ///
+    /// The plan is returned without touching the database when `dry_run` is set.
+    /// Otherwise each step is applied in order with `update_database`; plans that
+    /// remove or retype properties are refused unless `allow_destructive` is set.
+    pub async fn migrate_database(
+        &self,
+        database_id: &str,
+        desired: &DesiredSchema,
+        options: MigrationOptions,
+    ) -> Result<MigrationPlan, NotionError> {
+        let current = self.get_database(database_id).await?;
+        let plan = MigrationPlan::diff(&current.data, desired);
+        if options.dry_run {
+            return Ok(plan);
+        }
+        plan.check(&options)?;
+        for step in &plan.steps {
+            self.update_database(database_id, None, Some(step.properties_patch()))
+                .await?;
+        }
+        Ok(plan)
+    }
+
+    /// Queries a database with optional filters, sorting, and pagination.
+    /// Names in `filter_properties` are resolved to IDs using the database schema.
     pub async fn query_database(
         &self,
         database_id: &str,
         query: DatabaseQuery,
-    ) -> Result<ListResponse<Database>, NotionError> {
+    ) -> Result<ListResponse<Page>, NotionError> {
+        let query = self.resolve_filter_properties(database_id, query).await?;
+        self.query_database_page(database_id, query).await
+    }
+
+    /// Sends a query whose `filter_properties` are already property IDs
+    async fn query_database_page(
+        &self,
+        database_id: &str,
+        query: DatabaseQuery,
+    ) -> Result<ListResponse<Page>, NotionError> {
+        query.validate()?;
         let request = Database::query_request(database_id, query);
         self.post(request).await
     }
 
+    /// Queries a database, lazily following cursors until every matching page is returned
+    pub fn query_database_stream<'a>(
+        &'a self,
+        database_id: &'a str,
+        query: DatabaseQuery,
+    ) -> impl Stream<Item = Result<Page, NotionError>> + 'a {
+        // Resolve property names once rather than for every page of results
+        stream::once(self.resolve_filter_properties(database_id, query))
+            .map_ok(move |query| {
+                paginate(move |cursor| {
+                    let mut query = query.clone();
+                    query.start_cursor = cursor;
+                    self.query_database_page(database_id, query)
+                })
+            })
+            .try_flatten()
+    }
+
+    async fn resolve_filter_properties(
+        &self,
+        database_id: &str,
+        mut query: DatabaseQuery,
+    ) -> Result<DatabaseQuery, NotionError> {
+        if !query.filter_properties.is_empty() {
+            query.filter_properties = self
+                .resolve_property_ids(database_id, &query.filter_properties)
+                .await?;
+        }
+        Ok(query)
+    }
+
+    /// Resolves property names or IDs of a database to property IDs
+    pub async fn resolve_property_ids(
+        &self,
+        database_id: &str,
+        properties: &[String],
+    ) -> Result<Vec<String>, NotionError> {
+        let database = self.get_database(database_id).await?;
+        database.data.resolve_property_ids(properties)
+    }
+
+    /// Retrieves a page with only the given properties, identified by name or ID.
+    /// Names are resolved through the schema of the page's parent database; pages
+    /// outside a database only have the `title` property.
+    pub async fn get_page_with_properties(
+        &self,
+        page_id: &str,
+        properties: &[String],
+    ) -> Result<ObjectResponse<Page>, NotionError> {
+        // The title property always has the ID "title", so asking for it alone
+        // reveals the parent without loading every property
+        let title = ["title".to_string()];
+        let page: ObjectResponse<Page> = self
+            .get(Page::get_with_properties_request(page_id, &title))
+            .await?;
+        let property_ids = match page.data.parent["database_id"].as_str() {
+            Some(database_id) => self.resolve_property_ids(database_id, properties).await?,
+            None => properties.to_vec(),
+        };
+        self.get(Page::get_with_properties_request(page_id, &property_ids))
+            .await
+    }
+
+    /// Searches pages and databases shared with the integration
+    pub async fn search(
+        &self,
+        query: SearchQuery,
+    ) -> Result<ListResponse<SearchResult>, NotionError> {
+        self.post(query.request()).await
+    }
+
+    /// Searches pages and databases, lazily following cursors through every result
+    pub fn search_stream(
+        &self,
+        query: SearchQuery,
+    ) -> impl Stream<Item = Result<SearchResult, NotionError>> + '_ {
+        paginate(move |cursor| {
+            let mut query = query.clone();
+            query.start_cursor = cursor;
+            async move { self.search(query).await }
+        })
+    }
+
+    /// Lists one page of the children of a block or page
+    pub async fn list_children(
+        &self,
+        block_id: &str,
+        start_cursor: Option<&str>,
+    ) -> Result<ListResponse<Block>, NotionError> {
+        self.get(Block::children_request(block_id, start_cursor))
+            .await
+    }
+
+    /// Lists every child of a block or page, lazily following cursors
+    pub fn list_children_stream<'a>(
+        &'a self,
+        block_id: &'a str,
+    ) -> impl Stream<Item = Result<Block, NotionError>> + 'a {
+        paginate(move |cursor| async move { self.list_children(block_id, cursor.as_deref()).await })
+    }
+
+    /// Lists one page of the users in the workspace
+    pub async fn list_users(
+        &self,
+        start_cursor: Option<&str>,
+    ) -> Result<ListResponse<User>, NotionError> {
+        let mut request = RequestBuilder::new("/users").query_param("page_size", MAX_PAGE_SIZE);
+        if let Some(cursor) = start_cursor {
+            request = request.query_param("start_cursor", cursor);
+        }
+        self.get(request).await
+    }
+
+    /// Lists every user in the workspace, lazily following cursors
+    pub fn list_users_stream(&self) -> impl Stream<Item = Result<User, NotionError>> + '_ {
+        paginate(move |cursor| async move { self.list_users(cursor.as_deref()).await })
+    }
+
+    /// Retrieves a page and its property values
+    pub async fn get_page(&self, page_id: &str) -> Result<ObjectResponse<Page>, NotionError> {
+        self.get(Page::get_request(page_id)).await
+    }
+
+    /// Updates property values, icon, cover or archived state of a page
+    pub async fn update_page(
+        &self,
+        page_id: &str,
+        update: UpdatePage,
+    ) -> Result<ObjectResponse<Page>, NotionError> {
+        self.patch(update.request(page_id)).await
+    }
+
     /// Creates a new page as a child of another page
     pub async fn create_page(
         &self,
         parent_page_id: &str,
         title: &str,
     ) -> Result<ObjectResponse<Page>, NotionError> {
-        let request = RequestBuilder::new("/pages")
-            .method("POST")
-            .json_body(serde_json::json!({
-                "parent": { "page_id": parent_page_id },
-                "properties": {
-                    "title": {
-                        "title": [
-                            {
-                                "type": "text",
-                                "text": { "content": title }
-                            }
-                        ]
-                    }
-                }
-            }));
-        self.post(request).await
+        self.create_page_with(CreatePage::new(Parent::page(parent_page_id)).title(title))
+            .await
     }
 
-    /// Creates a new page in a database
+    /// Creates a new page in a database, titled through its title property
     pub async fn create_database_page(
         &self,
         database_id: &str,
         title: &str,
     ) -> Result<ObjectResponse<Page>, NotionError> {
-        let request = RequestBuilder::new("/pages")
-            .method("POST")
-            .json_body(serde_json::json!({
-                "parent": { "database_id": database_id },
-                "properties": {
-                    "Name": {
-                        "title": [
-                            {
-                                "type": "text",
-                                "text": { "content": title }
-                            }
-                        ]
-                    }
-                }
-            }));
-        self.post(request).await
+        self.create_page_with(CreatePage::new(Parent::database(database_id)).title(title))
+            .await
+    }
+
+    /// Creates a page with any properties, icon, cover and content.
+    ///
+    /// Content beyond the first `MAX_CHILDREN` blocks is appended after the
+    /// page is created. If appending fails, the page is moved to the trash
+    /// rather than left with part of its content, and the error is returned.
+    pub async fn create_page_with(
+        &self,
+        page: CreatePage,
+    ) -> Result<ObjectResponse<Page>, NotionError> {
+        let created: ObjectResponse<Page> = self.post(page.request()).await?;
+        for children in page.remaining_children().chunks(MAX_CHILDREN) {
+            if let Err(error) = self
+                .patch::<ListResponse<Block>>(Block::append_children_request(
+                    &created.data.id,
+                    children,
+                ))
+                .await
+            {
+                let _ = self
+                    .patch::<ObjectResponse<Page>>(Page::archive_request(&created.data.id, true))
+                    .await;
+                return Err(error);
+            }
+        }
+        Ok(created)
+    }
+
+    /// Moves a page to the trash
+    pub async fn archive_page(&self, page_id: &str) -> Result<ObjectResponse<Page>, NotionError> {
+        let current = self.get_page(page_id).await?;
+        if current.data.is_archived() {
+            return Err(NotionError::AlreadyArchived(page_id.to_string()));
+        }
+        self.patch(Page::archive_request(page_id, true)).await
+    }
+
+    /// Restores a page from the trash
+    pub async fn restore_page(&self, page_id: &str) -> Result<ObjectResponse<Page>, NotionError> {
+        let current = self.get_page(page_id).await?;
+        if !current.data.is_archived() {
+            return Err(NotionError::NotArchived(page_id.to_string()));
+        }
+        self.patch(Page::archive_request(page_id, false)).await
     }
 }
@@ -0,0 +1,233 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use crate::database::{plain_text, Database};
+use crate::export::default_columns;
+use crate::schema::{PropertySchema, SelectOption};
+
+/// Options for `generate_record`
+#[derive(Debug, Clone, Default)]
+pub struct CodegenOptions {
+    /// Name of the generated struct. Defaults to the database title in
+    /// PascalCase.
+    pub struct_name: Option<String>,
+}
+
+/// Strict and reserved keywords. Some of them, such as `self` and `crate`,
+/// cannot be raw identifiers, so all of them are suffixed with `_` instead.
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Alphanumeric words of a name, split at other characters and at lowercase
+/// to uppercase transitions
+fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if !c.is_alphanumeric() {
+            words.extend((!word.is_empty()).then(|| std::mem::take(&mut word)));
+            previous_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && previous_lower {
+            words.push(std::mem::take(&mut word));
+        }
+        previous_lower = c.is_lowercase() || c.is_numeric();
+        word.push(c);
+    }
+    words.extend((!word.is_empty()).then_some(word));
+    words
+}
+
+/// Makes a name a valid identifier that differs from the ones already used.
+/// Non-ASCII letters are kept, since Rust accepts them in identifiers.
+fn unique_identifier(name: String, fallback: &str, used: &mut HashSet<String>) -> String {
+    let mut name = match name.chars().next() {
+        None => fallback.to_string(),
+        Some(c) if c.is_numeric() => format!("{}{}", fallback, name),
+        Some(_) if KEYWORDS.contains(&name.as_str()) => format!("{}_", name),
+        Some(_) => name,
+    };
+    let base = name.clone();
+    let mut suffix = 2;
+    while !used.insert(name.clone()) {
+        name = format!("{}{}", base, suffix);
+        suffix += 1;
+    }
+    name
+}
+
+fn pascal_case(name: &str) -> String {
+    words(name)
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| {
+                    first
+                        .to_uppercase()
+                        .chain(chars.flat_map(char::to_lowercase))
+                })
+                .into_iter()
+                .flatten()
+                .collect::<String>()
+        })
+        .collect()
+}
+
+fn snake_case(name: &str) -> String {
+    words(name)
+        .iter()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Rust type of the field for a property, or `None` for property types the
+/// crate does not model
+fn field_type(schema: &PropertySchema, enum_name: Option<&str>) -> Option<String> {
+    Some(match schema {
+        PropertySchema::Title => "String".to_string(),
+        PropertySchema::Checkbox => "bool".to_string(),
+        PropertySchema::Number(_) => "Option<f64>".to_string(),
+        PropertySchema::Select(_) | PropertySchema::Status(_) => match enum_name {
+            Some(name) => format!("Option<{}>", name),
+            None => "Option<String>".to_string(),
+        },
+        PropertySchema::MultiSelect(_)
+        | PropertySchema::People
+        | PropertySchema::Files
+        | PropertySchema::Relation(_) => "Vec<String>".to_string(),
+        PropertySchema::Unknown { .. } => return None,
+        _ => "Option<String>".to_string(),
+    })
+}
+
+fn select_options(schema: &PropertySchema) -> &[SelectOption] {
+    match schema {
+        PropertySchema::Select(config) => &config.options,
+        PropertySchema::Status(config) => &config.options,
+        _ => &[],
+    }
+}
+
+/// Generates Rust source for a struct deriving `NotionRecord` that matches
+/// the schema of a database, with an enum for the options of every select and
+/// status property.
+///
+/// The enums serialize to the option names and implement `PropertyField`, so
+/// the generated code needs `serde` with the `derive` feature. Multi-select
+/// values stay `Vec<String>`.
+pub fn generate_record(database: &Database, options: &CodegenOptions) -> String {
+    let title = plain_text(&database.title);
+    let struct_name = options.struct_name.clone().unwrap_or_else(|| {
+        let name = pascal_case(&title);
+        unique_identifier(name, "Record", &mut HashSet::new())
+    });
+
+    let mut types = HashSet::from([struct_name.clone()]);
+    let mut enums = String::new();
+    let mut fields = String::new();
+    let mut field_names = HashSet::new();
+
+    for name in &default_columns(database) {
+        let property = &database.properties[name];
+        let schema = &property.schema;
+        let property_options = select_options(schema);
+        let enum_name = (!property_options.is_empty()).then(|| {
+            let name = format!("{}{}", struct_name, pascal_case(name));
+            unique_identifier(name, "Options", &mut types)
+        });
+
+        let Some(rust_type) = field_type(schema, enum_name.as_deref()) else {
+            let _ = writeln!(
+                fields,
+                "    // \"{}\" is skipped: unsupported property type \"{}\"",
+                name.escape_default(),
+                schema.type_name()
+            );
+            continue;
+        };
+
+        if let Some(enum_name) = &enum_name {
+            let mut variants = HashSet::new();
+            let _ = writeln!(enums, "/// Options of the {:?} property", name);
+            let _ = writeln!(
+                enums,
+                "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]"
+            );
+            let _ = writeln!(enums, "pub enum {} {{", enum_name);
+            for option in property_options {
+                let variant = unique_identifier(pascal_case(&option.name), "Option", &mut variants);
+                if variant != option.name {
+                    let _ = writeln!(enums, "    #[serde(rename = {:?})]", option.name);
+                }
+                let _ = writeln!(enums, "    {},", variant);
+            }
+            let _ = writeln!(enums, "}}\n");
+            let _ = writeln!(
+                enums,
+                "impl PropertyField for {} {{
+    fn default_type() -> &'static str {{
+        {:?}
+    }}
+
+    fn to_property_value(&self, property_type: &str) -> Result<Value, String> {{
+        option_to_property_value(self, property_type)
+    }}
+
+    fn from_property_value(value: &Value) -> Result<Self, String> {{
+        option_from_property_value(value)
+    }}
+}}
+",
+                enum_name,
+                schema.type_name()
+            );
+        }
+
+        let mut attribute = format!("name = {:?}, type = {:?}", name, schema.type_name());
+        match schema {
+            PropertySchema::Relation(config) => {
+                let _ = write!(attribute, ", database_id = {:?}", config.database_id);
+            }
+            PropertySchema::Formula(config) => {
+                let _ = write!(attribute, ", expression = {:?}", config.expression);
+            }
+            _ => {}
+        }
+        let field_name = snake_case(name);
+        let field_name = unique_identifier(field_name, "field_", &mut field_names);
+        let _ = writeln!(fields, "    #[notion({})]", attribute);
+        let _ = writeln!(fields, "    pub {}: {},", field_name, rust_type);
+    }
+
+    let mut source = format!(
+        "// Generated from the {:?} database ({}). Do not edit by hand.\n\n",
+        title, database.id
+    );
+    if !enums.is_empty() {
+        source.push_str(
+            "use notion_rs::record::{\n    option_from_property_value, option_to_property_value, PropertyField, Value,\n};\n",
+        );
+    }
+    source.push_str("use notion_rs::NotionRecord;\n");
+    if !enums.is_empty() {
+        source.push_str("use serde::{Deserialize, Serialize};\n");
+    }
+    source.push('\n');
+    source.push_str(&enums);
+    let _ = write!(
+        source,
+        "#[derive(Debug, Clone, NotionRecord)]\npub struct {} {{\n{}}}\n",
+        struct_name, fields
+    );
+    source
+}
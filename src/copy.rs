@@ -0,0 +1,620 @@
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use crate::block::Block;
+use crate::client::NotionClient;
+use crate::database::same_id;
+use crate::duplicate::{writable_property_value, DuplicateOptions};
+use crate::error::NotionError;
+use crate::file::{File, Icon};
+use crate::page::{CreatePage, Page, Parent};
+use crate::page_move::moved_property_name;
+use crate::request::RequestBuilder;
+use crate::upload::link_hosted_files;
+
+/// Options for `NotionClient::copy_page`
+#[derive(Debug, Clone)]
+pub struct CopyPageOptions {
+    /// Whether both clients belong to the same workspace, so that the users,
+    /// pages and databases the page references can be kept
+    pub same_workspace: bool,
+    /// IDs of users of the source workspace mapped to users of the target
+    /// workspace. In another workspace, people that are not mapped are left
+    /// out and mentions of them become plain text.
+    pub users: HashMap<String, String>,
+    /// Upload files hosted by Notion to the target. Otherwise they are
+    /// linked by their current URL, which expires after an hour.
+    pub upload_files: bool,
+    /// Also copy child pages and databases
+    pub recursive: bool,
+}
+
+impl Default for CopyPageOptions {
+    fn default() -> Self {
+        Self {
+            same_workspace: false,
+            users: HashMap::new(),
+            upload_files: true,
+            recursive: true,
+        }
+    }
+}
+
+/// A page created by `NotionClient::copy_page`
+#[derive(Debug, Clone)]
+pub struct CopiedPage {
+    pub page: Page,
+    /// IDs of the source page, its blocks and the child pages and databases
+    /// copied with it, mapped to the IDs of their copies
+    pub ids: HashMap<String, String>,
+}
+
+/// Converts what is copied from one client to another, so that the copy only
+/// references objects that exist where it is created
+pub(crate) trait CopyValues: Sync {
+    /// Request value creating a copy of a block. `None` exactly when
+    /// `Block::to_request_value` is `None`.
+    fn block<'a>(&'a self, block: &'a Block) -> BoxFuture<'a, Result<Option<Value>, NotionError>>;
+
+    /// Value to set on the copy of a page for the value of its property
+    /// `name`, or `None` to leave the property out
+    fn property<'a>(
+        &'a self,
+        page_id: &'a str,
+        name: &'a str,
+        value: &'a Value,
+    ) -> BoxFuture<'a, Result<Option<Value>, NotionError>>;
+
+    fn icon<'a>(&'a self, icon: &'a Icon) -> BoxFuture<'a, Result<Option<Icon>, NotionError>>;
+
+    fn cover<'a>(&'a self, cover: &'a File) -> BoxFuture<'a, Result<File, NotionError>>;
+
+    /// Database that a relation to `database_id` points to in the copy, or
+    /// `None` to leave out the relation property
+    fn related_database(&self, database_id: &str) -> Option<String>;
+}
+
+/// Copies within one workspace: everything is kept as it is, apart from
+/// files hosted by Notion, whose URLs expire
+pub(crate) struct SameWorkspace<'c> {
+    /// Client that hosted files are uploaded to again. When `None`, they are
+    /// kept as they are, to be uploaded once the values are sent.
+    pub(crate) target: Option<&'c NotionClient>,
+}
+
+impl SameWorkspace<'_> {
+    async fn upload(&self, mut value: Value) -> Result<Value, NotionError> {
+        if let Some(target) = self.target {
+            target.upload_hosted_files(&mut value).await?;
+        }
+        Ok(value)
+    }
+}
+
+impl CopyValues for SameWorkspace<'_> {
+    fn block<'a>(&'a self, block: &'a Block) -> BoxFuture<'a, Result<Option<Value>, NotionError>> {
+        async move {
+            match block.to_request_value() {
+                Some(value) => Ok(Some(self.upload(value).await?)),
+                None => Ok(None),
+            }
+        }
+        .boxed()
+    }
+
+    fn property<'a>(
+        &'a self,
+        _page_id: &'a str,
+        _name: &'a str,
+        value: &'a Value,
+    ) -> BoxFuture<'a, Result<Option<Value>, NotionError>> {
+        async move {
+            match writable_property_value(value) {
+                Some(value) => Ok(Some(self.upload(value).await?)),
+                None => Ok(None),
+            }
+        }
+        .boxed()
+    }
+
+    fn icon<'a>(&'a self, icon: &'a Icon) -> BoxFuture<'a, Result<Option<Icon>, NotionError>> {
+        async move {
+            match self.target {
+                Some(target) => target.upload_hosted_icon(icon).await,
+                None => Ok(Some(icon.clone())),
+            }
+        }
+        .boxed()
+    }
+
+    fn cover<'a>(&'a self, cover: &'a File) -> BoxFuture<'a, Result<File, NotionError>> {
+        async move {
+            match self.target {
+                Some(target) => target.upload_hosted_cover(cover).await,
+                None => Ok(cover.clone()),
+            }
+        }
+        .boxed()
+    }
+
+    fn related_database(&self, database_id: &str) -> Option<String> {
+        Some(database_id.to_string())
+    }
+}
+
+/// Public URL of a page or database, linked in place of references that
+/// cannot be carried over to another workspace
+fn notion_url(id: &str) -> String {
+    format!("https://www.notion.so/{}", id.replace('-', ""))
+}
+
+/// Rich text item with the plain text of `item`, keeping its annotations
+fn text_item(item: &Value, link: Option<&str>) -> Value {
+    json!({
+        "type": "text",
+        "text": {
+            "content": item["plain_text"],
+            "link": link.map(|url| json!({ "url": url })),
+        },
+        "annotations": item["annotations"],
+    })
+}
+
+/// Something copied while the pages or databases it references were not
+/// copied yet
+enum Deferred {
+    Block(Block),
+    Property {
+        page_id: String,
+        name: String,
+        value: Value,
+    },
+}
+
+/// Conversion for `NotionClient::copy_page`, which may copy to another
+/// workspace
+pub(crate) struct PageCopy<'a> {
+    source: &'a NotionClient,
+    target: &'a NotionClient,
+    options: &'a CopyPageOptions,
+    /// Pages, databases and blocks copied so far
+    ids: Mutex<HashMap<String, String>>,
+    /// IDs that `reference` did not find a copy for during the current
+    /// conversion
+    missed: Mutex<Vec<String>>,
+    /// Blocks and property values converted while something they reference
+    /// was not copied yet, with the IDs they missed
+    deferred: Mutex<Vec<(Deferred, Vec<String>)>>,
+}
+
+impl<'a> PageCopy<'a> {
+    pub(crate) fn new(
+        source: &'a NotionClient,
+        target: &'a NotionClient,
+        options: &'a CopyPageOptions,
+    ) -> Self {
+        Self {
+            source,
+            target,
+            options,
+            ids: Mutex::new(HashMap::new()),
+            missed: Mutex::new(Vec::new()),
+            deferred: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Records copies, keyed by the IDs of their originals
+    pub(crate) fn record(&self, ids: impl IntoIterator<Item = (String, String)>) {
+        self.ids.lock().unwrap().extend(ids);
+    }
+
+    /// The copy of a page, database or block copied so far
+    pub(crate) fn copy_of(&self, id: &str) -> Option<String> {
+        let ids = self.ids.lock().unwrap();
+        ids.iter()
+            .find(|(original, _)| same_id(original, id))
+            .map(|(_, copy)| copy.clone())
+    }
+
+    /// Every copy made, keyed by the ID of its original
+    pub(crate) fn into_ids(self) -> HashMap<String, String> {
+        self.ids.into_inner().unwrap()
+    }
+
+    /// Takes the IDs missed since the last call, so that a conversion only
+    /// sees its own
+    fn take_missed(&self) -> Vec<String> {
+        std::mem::take(&mut *self.missed.lock().unwrap())
+    }
+
+    fn user(&self, id: &str) -> Option<String> {
+        if self.options.same_workspace {
+            Some(id.to_string())
+        } else {
+            self.options.users.get(id).cloned()
+        }
+    }
+
+    /// The copy of a page or database, or the original when it stays valid
+    fn reference(&self, id: &str) -> Option<String> {
+        let copied = self.ids.lock().unwrap().get(id).cloned();
+        if copied.is_none() {
+            self.missed.lock().unwrap().push(id.to_string());
+        }
+        copied.or_else(|| self.options.same_workspace.then(|| id.to_string()))
+    }
+
+    /// Relation items pointing to the copies of the related pages
+    fn relation(&self, items: &Value) -> Value {
+        items
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|page| self.reference(page["id"].as_str()?))
+            .map(|id| json!({ "id": id }))
+            .collect()
+    }
+
+    /// Mentions of users, pages and databases that are not available in the
+    /// target become plain text, linked to the original where there is one
+    fn rich_text(&self, items: &Value) -> Value {
+        let Some(items) = items.as_array() else {
+            return items.clone();
+        };
+        let convert = |item: &Value| {
+            let mention = &item["mention"];
+            let kind = mention["type"].as_str().unwrap_or_default();
+            let id = mention[kind]["id"].as_str().unwrap_or_default();
+            let mut item = item.clone();
+            match kind {
+                _ if item["type"] != "mention" => item,
+                "date" | "template_mention" => item,
+                "user" => match self.user(id) {
+                    Some(id) => {
+                        item["mention"]["user"] = json!({ "id": id });
+                        item
+                    }
+                    None => text_item(&item, None),
+                },
+                "page" | "database" => match self.reference(id) {
+                    Some(id) => {
+                        item["mention"][kind] = json!({ "id": id });
+                        item
+                    }
+                    None => text_item(&item, Some(&notion_url(id))),
+                },
+                _ => text_item(&item, item["href"].as_str()),
+            }
+        };
+        items.iter().map(convert).collect()
+    }
+
+    /// Uploads the files hosted by Notion in a request value to the target,
+    /// or links them by their current URL when the options say so
+    async fn files(&self, value: &mut Value) -> Result<(), NotionError> {
+        if self.options.upload_files {
+            self.target.upload_hosted_files(value).await
+        } else {
+            link_hosted_files(value);
+            Ok(())
+        }
+    }
+
+    /// Request value creating a copy of a block, for `CopyValues::block`
+    async fn block_value(&self, block: &Block) -> Result<Option<Value>, NotionError> {
+        let Some(mut value) = block.to_request_value() else {
+            return Ok(None);
+        };
+        let content = &mut value[&block.block_type];
+        for key in ["rich_text", "caption"] {
+            if content[key].is_array() {
+                content[key] = self.rich_text(&content[key]);
+            }
+        }
+        if let Some(cells) = content["cells"].as_array_mut() {
+            for cell in cells {
+                *cell = self.rich_text(cell);
+            }
+        }
+        if block.block_type == "link_to_page" {
+            let kind = content["type"].as_str().unwrap_or_default().to_string();
+            let id = content[&kind].as_str().unwrap_or_default().to_string();
+            match self.reference(&id) {
+                Some(copy) => content[&kind] = json!(copy),
+                None => {
+                    let url = notion_url(&id);
+                    let text = json!({ "plain_text": url });
+                    return Ok(Some(json!({
+                        "object": "block",
+                        "type": "paragraph",
+                        "paragraph": { "rich_text": [text_item(&text, Some(&url))] },
+                    })));
+                }
+            }
+        }
+        if block.block_type == "synced_block" && !self.options.same_workspace {
+            // The original synced block is not copied; the content of
+            // this one is copied into a new original
+            content["synced_from"] = Value::Null;
+        }
+        self.files(&mut value).await?;
+        Ok(Some(value))
+    }
+
+    /// Value to set on the copy of a page, for `CopyValues::property`
+    async fn property_value(&self, value: &Value) -> Result<Option<Value>, NotionError> {
+        let Some(mut writable) = writable_property_value(value) else {
+            return Ok(None);
+        };
+        let kind = value["type"].as_str().unwrap_or_default();
+        let items = value[kind].as_array().into_iter().flatten();
+        writable[kind] = match kind {
+            "title" | "rich_text" => self.rich_text(&writable[kind]),
+            "people" => items
+                .filter_map(|user| self.user(user["id"].as_str()?))
+                .map(|id| json!({ "id": id }))
+                .collect(),
+            "relation" => self.relation(&value[kind]),
+            "files" => {
+                self.files(&mut writable).await?;
+                return Ok(Some(writable));
+            }
+            _ => return Ok(Some(writable)),
+        };
+        Ok(Some(writable))
+    }
+
+    /// Copies a page with its content, and its child pages and databases when
+    /// the options ask for it
+    pub(crate) fn copy_page<'b>(
+        &'b self,
+        page_id: &'b str,
+        parent: Parent,
+    ) -> BoxFuture<'b, Result<Page, NotionError>> {
+        async move {
+            let mut page = self.source.get_page(page_id).await?.data;
+            self.source.complete_list_properties(&mut page).await?;
+            let database = match &parent {
+                Parent::Database { database_id } => {
+                    Some(self.target.get_database(database_id).await?.data)
+                }
+                _ => None,
+            };
+
+            let mut create = CreatePage::new(parent);
+            for (name, value) in page.properties.as_object().into_iter().flatten() {
+                let kind = value["type"].as_str().unwrap_or_default();
+                let Some(key) = moved_property_name(name, kind, database.as_ref()) else {
+                    continue;
+                };
+                if let Some(value) = self.property(&page.id, name, value).await? {
+                    create = create.property(&key, value);
+                }
+            }
+            if let Some(icon) = &page.icon {
+                if let Some(icon) = self.icon(icon).await? {
+                    create = create.icon(icon);
+                }
+            }
+            if let Some(cover) = &page.cover {
+                create = create.cover(self.cover(cover).await?);
+            }
+            let copy = self.target.create_page_with(create).await?.data;
+            self.record([(page.id.clone(), copy.id.clone())]);
+
+            let blocks = self
+                .source
+                .copy_block_children_to(&page.id, self.target, &copy.id, self)
+                .await?;
+            self.record(blocks);
+
+            if self.options.recursive {
+                for child in self.source.list_nested_pages(&page.id).await? {
+                    if child.block_type == "child_page" {
+                        self.copy_page(&child.id, Parent::page(&copy.id)).await?;
+                        continue;
+                    }
+                    let options = DuplicateOptions {
+                        title: None,
+                        copy_content: true,
+                        dual_external_relations: false,
+                    };
+                    let duplicated = self
+                        .source
+                        .duplicate_database_to(&child.id, self.target, &copy.id, options, self)
+                        .await?;
+                    self.record(duplicated.ids);
+                }
+            }
+            Ok(copy)
+        }
+        .boxed()
+    }
+
+    /// Requests pointing the references that were converted before the
+    /// pages or databases they reference were copied at those copies
+    pub(crate) fn reference_updates(&self) -> Vec<RequestBuilder> {
+        let ids = self.ids.lock().unwrap().clone();
+        let deferred = std::mem::take(&mut *self.deferred.lock().unwrap());
+        let mut requests = Vec::new();
+        let mut pages: BTreeMap<String, Map<String, Value>> = BTreeMap::new();
+        for (object, missed) in deferred {
+            if !missed.iter().any(|id| ids.contains_key(id)) {
+                continue;
+            }
+            match object {
+                Deferred::Block(block) => {
+                    let Some(copy_id) = ids.get(&block.id) else {
+                        continue;
+                    };
+                    let content = &block.content[&block.block_type];
+                    let update = if block.block_type == "link_to_page" {
+                        let kind = content["type"].as_str().unwrap_or_default();
+                        let Some(linked) = ids.get(content[kind].as_str().unwrap_or_default())
+                        else {
+                            continue;
+                        };
+                        if self.options.same_workspace {
+                            json!({ "link_to_page": { "type": kind, kind: linked } })
+                        } else {
+                            // The link was copied as a paragraph; mention the copy instead
+                            let mention = kind.trim_end_matches("_id");
+                            json!({ "paragraph": { "rich_text": [{
+                                "type": "mention",
+                                "mention": { "type": mention, mention: { "id": linked } },
+                            }] } })
+                        }
+                    } else {
+                        let mut fields = Map::new();
+                        for key in ["rich_text", "caption"] {
+                            if content[key].is_array() {
+                                fields.insert(key.to_string(), self.rich_text(&content[key]));
+                            }
+                        }
+                        if let Some(cells) = content["cells"].as_array() {
+                            let cells = cells.iter().map(|cell| self.rich_text(cell)).collect();
+                            fields.insert("cells".to_string(), cells);
+                        }
+                        json!({ block.block_type.clone(): fields })
+                    };
+                    requests.push(Block::update_request(copy_id, update));
+                }
+                Deferred::Property {
+                    page_id,
+                    name,
+                    value,
+                } => {
+                    let (Some(copy_id), Some(mut writable)) =
+                        (ids.get(&page_id), writable_property_value(&value))
+                    else {
+                        continue;
+                    };
+                    let kind = value["type"].as_str().unwrap_or_default();
+                    writable[kind] = match kind {
+                        "relation" => self.relation(&value[kind]),
+                        _ => self.rich_text(&writable[kind]),
+                    };
+                    // The title property has this ID in every database
+                    let key = if kind == "title" {
+                        "title".to_string()
+                    } else {
+                        name
+                    };
+                    pages
+                        .entry(copy_id.clone())
+                        .or_default()
+                        .insert(key, writable);
+                }
+            }
+        }
+        requests.extend(pages.into_iter().map(|(copy_id, properties)| {
+            Page::update_properties_request(&copy_id, Value::Object(properties))
+        }));
+        requests
+    }
+}
+
+impl CopyValues for PageCopy<'_> {
+    fn block<'a>(&'a self, block: &'a Block) -> BoxFuture<'a, Result<Option<Value>, NotionError>> {
+        async move {
+            self.take_missed();
+            let value = self.block_value(block).await?;
+            let missed = self.take_missed();
+            if !missed.is_empty() {
+                let deferred = (Deferred::Block(block.clone()), missed);
+                self.deferred.lock().unwrap().push(deferred);
+            }
+            Ok(value)
+        }
+        .boxed()
+    }
+
+    fn property<'a>(
+        &'a self,
+        page_id: &'a str,
+        name: &'a str,
+        value: &'a Value,
+    ) -> BoxFuture<'a, Result<Option<Value>, NotionError>> {
+        async move {
+            self.take_missed();
+            let converted = self.property_value(value).await?;
+            let missed = self.take_missed();
+            if !missed.is_empty() {
+                let deferred = Deferred::Property {
+                    page_id: page_id.to_string(),
+                    name: name.to_string(),
+                    value: value.clone(),
+                };
+                self.deferred.lock().unwrap().push((deferred, missed));
+            }
+            Ok(converted)
+        }
+        .boxed()
+    }
+
+    fn icon<'a>(&'a self, icon: &'a Icon) -> BoxFuture<'a, Result<Option<Icon>, NotionError>> {
+        async move {
+            Ok(match icon {
+                Icon::File { .. } if self.options.upload_files => {
+                    self.target.upload_hosted_icon(icon).await?
+                }
+                // Custom emojis belong to a workspace
+                Icon::CustomEmoji { .. } if !self.options.same_workspace => None,
+                icon => icon.linked(),
+            })
+        }
+        .boxed()
+    }
+
+    fn cover<'a>(&'a self, cover: &'a File) -> BoxFuture<'a, Result<File, NotionError>> {
+        async move {
+            Ok(if self.options.upload_files {
+                self.target.upload_hosted_cover(cover).await?
+            } else {
+                cover.linked()
+            })
+        }
+        .boxed()
+    }
+
+    fn related_database(&self, database_id: &str) -> Option<String> {
+        self.reference(database_id)
+    }
+}
+
+impl NotionClient {
+    /// Copies a page into a page or database that `target` can access,
+    /// which may be a client for another workspace.
+    ///
+    /// Properties are copied as by `move_page` (the title, plus the
+    /// properties the target database has with the same name and type), along
+    /// with the icon, cover and the full block tree. Child pages and
+    /// databases are copied recursively, after the rest of the content.
+    /// References to pages and databases that were copied point to their
+    /// copies; references that were converted before their target was copied
+    /// are updated once everything is copied. Other references are kept
+    /// within one workspace. In another, users are mapped through
+    /// `CopyPageOptions::users`, and anything else is left out or turned into
+    /// a link to the original, including relation properties to databases
+    /// that were not copied before the database holding them.
+    pub async fn copy_page(
+        &self,
+        page_id: &str,
+        target: &NotionClient,
+        target_parent: Parent,
+        options: CopyPageOptions,
+    ) -> Result<CopiedPage, NotionError> {
+        let copy = PageCopy::new(self, target, &options);
+        let page = copy.copy_page(page_id, target_parent).await?;
+        for request in copy.reference_updates() {
+            target.patch::<Value>(request).await?;
+        }
+        Ok(CopiedPage {
+            page,
+            ids: copy.into_ids(),
+        })
+    }
+}
@@ -1,26 +1,318 @@
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
 
+use crate::error::NotionError;
+use crate::file::{File, Icon};
 use crate::request::RequestBuilder;
+use crate::schema::{
+    merge_select_options, DatabaseProperty, DatabasePropertyUpdate, PropertyRef, PropertySchema,
+};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Database {
     pub id: String,
     pub title: Vec<RichText>,
-    pub properties: Value,
+    #[serde(default)]
+    pub description: Vec<RichText>,
+    #[serde(default)]
+    pub icon: Option<Icon>,
+    #[serde(default)]
+    pub cover: Option<File>,
+    /// Whether the database is displayed inline in its parent page
+    #[serde(default)]
+    pub is_inline: bool,
+    /// Database schema, keyed by property name
+    pub properties: BTreeMap<String, DatabaseProperty>,
     pub url: String,
     pub created_time: String,
     pub last_edited_time: String,
+    /// Whether the database has been archived
+    #[serde(default)]
+    pub archived: bool,
+    /// Whether the database has been moved to the trash
+    #[serde(default)]
+    pub in_trash: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RichText {
+    /// Kind of rich text: `text`, `mention` or `equation`
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub rich_text_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<TextContent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Annotations>,
+    #[serde(default)]
     pub plain_text: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub href: Option<String>,
+    /// Mention or equation content
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextContent {
+    pub content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<Link>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Link {
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Annotations {
+    #[serde(default)]
+    pub bold: bool,
+    #[serde(default)]
+    pub italic: bool,
+    #[serde(default)]
+    pub strikethrough: bool,
+    #[serde(default)]
+    pub underline: bool,
+    #[serde(default)]
+    pub code: bool,
+    #[serde(default = "default_color")]
+    pub color: String,
+}
+
+fn default_color() -> String {
+    "default".to_string()
+}
+
+impl RichText {
+    /// Creates a plain text segment
+    pub fn text(content: &str) -> Self {
+        Self {
+            rich_text_type: Some("text".to_string()),
+            text: Some(TextContent {
+                content: content.to_string(),
+                link: None,
+            }),
+            annotations: None,
+            plain_text: content.to_string(),
+            href: None,
+            other: Map::new(),
+        }
+    }
+
+    /// Creates a text segment linking to the given URL
+    pub fn link(content: &str, url: &str) -> Self {
+        let mut rich_text = Self::text(content);
+        if let Some(text) = &mut rich_text.text {
+            text.link = Some(Link {
+                url: url.to_string(),
+            });
+        }
+        rich_text.href = Some(url.to_string());
+        rich_text
+    }
+
+    /// Sets the annotations (bold, italic, color, ...) of the segment
+    pub fn with_annotations(mut self, annotations: Annotations) -> Self {
+        self.annotations = Some(annotations);
+        self
+    }
+}
+
+/// Concatenates the plain text of rich text segments
+pub fn plain_text(rich_text: &[RichText]) -> String {
+    rich_text.iter().map(|t| t.plain_text.as_str()).collect()
+}
+
+/// Fields shared by database create and update requests
+#[derive(Debug, Clone, Default, Serialize)]
+struct DatabaseFields {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<Vec<RichText>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<Vec<RichText>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon: Option<Icon>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cover: Option<File>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    is_inline: Option<bool>,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    properties: Map<String, Value>,
+}
+
+impl DatabaseFields {
+    fn property(&mut self, name: &str, schema: &PropertySchema) {
+        self.properties
+            .insert(name.to_string(), schema.to_request_value());
+    }
+
+    fn properties(&mut self, properties: Value) {
+        if let Value::Object(properties) = properties {
+            self.properties.extend(properties);
+        }
+    }
+}
+
+/// Builder for a `POST /databases` request
+#[derive(Debug, Clone)]
+pub struct CreateDatabase {
+    parent_page_id: String,
+    fields: DatabaseFields,
+}
+
+impl CreateDatabase {
+    /// Starts a database to be created inside the given page
+    pub fn new(parent_page_id: &str) -> Self {
+        Self {
+            parent_page_id: parent_page_id.to_string(),
+            fields: DatabaseFields::default(),
+        }
+    }
+
+    /// Sets a plain text title
+    pub fn title(self, title: &str) -> Self {
+        self.title_rich_text(vec![RichText::text(title)])
+    }
+
+    pub fn title_rich_text(mut self, title: Vec<RichText>) -> Self {
+        self.fields.title = Some(title);
+        self
+    }
+
+    /// Sets a plain text description
+    pub fn description(self, description: &str) -> Self {
+        self.description_rich_text(vec![RichText::text(description)])
+    }
+
+    pub fn description_rich_text(mut self, description: Vec<RichText>) -> Self {
+        self.fields.description = Some(description);
+        self
+    }
+
+    pub fn icon(mut self, icon: Icon) -> Self {
+        self.fields.icon = Some(icon);
+        self
+    }
+
+    pub fn cover(mut self, cover: File) -> Self {
+        self.fields.cover = Some(cover);
+        self
+    }
+
+    /// Displays the database inline in the parent page instead of as a child page
+    pub fn is_inline(mut self, is_inline: bool) -> Self {
+        self.fields.is_inline = Some(is_inline);
+        self
+    }
+
+    /// Adds a property to the schema
+    pub fn property(mut self, name: &str, schema: &PropertySchema) -> Self {
+        self.fields.property(name, schema);
+        self
+    }
+
+    /// Adds raw property schema objects, keyed by property name
+    pub fn properties(mut self, properties: Value) -> Self {
+        self.fields.properties(properties);
+        self
+    }
+
+    pub fn request(&self) -> RequestBuilder {
+        let mut body = serde_json::to_value(&self.fields).unwrap();
+        body["parent"] = serde_json::json!({ "type": "page_id", "page_id": self.parent_page_id });
+        if body.get("title").is_none() {
+            body["title"] = serde_json::json!([]);
+        }
+        if body.get("properties").is_none() {
+            body["properties"] = serde_json::json!({});
+        }
+        RequestBuilder::new("/databases")
+            .method("POST")
+            .json_body(body)
+    }
+}
+
+/// Builder for a `PATCH /databases/{id}` request; only the fields that are set are sent
+#[derive(Debug, Clone, Default)]
+pub struct UpdateDatabase {
+    fields: DatabaseFields,
+}
+
+impl UpdateDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets a plain text title
+    pub fn title(self, title: &str) -> Self {
+        self.title_rich_text(vec![RichText::text(title)])
+    }
+
+    pub fn title_rich_text(mut self, title: Vec<RichText>) -> Self {
+        self.fields.title = Some(title);
+        self
+    }
+
+    /// Sets a plain text description
+    pub fn description(self, description: &str) -> Self {
+        self.description_rich_text(vec![RichText::text(description)])
+    }
+
+    pub fn description_rich_text(mut self, description: Vec<RichText>) -> Self {
+        self.fields.description = Some(description);
+        self
+    }
+
+    pub fn icon(mut self, icon: Icon) -> Self {
+        self.fields.icon = Some(icon);
+        self
+    }
+
+    pub fn cover(mut self, cover: File) -> Self {
+        self.fields.cover = Some(cover);
+        self
+    }
+
+    pub fn is_inline(mut self, is_inline: bool) -> Self {
+        self.fields.is_inline = Some(is_inline);
+        self
+    }
+
+    /// Adds or replaces a property in the schema
+    pub fn property(mut self, name: &str, schema: &PropertySchema) -> Self {
+        self.fields.property(name, schema);
+        self
+    }
+
+    /// Adds raw property updates, keyed by property name or ID
+    pub fn properties(mut self, properties: Value) -> Self {
+        self.fields.properties(properties);
+        self
+    }
+
+    pub fn request(&self, database_id: &str) -> RequestBuilder {
+        RequestBuilder::new(&format!("/databases/{}", database_id))
+            .method("PATCH")
+            .json_body(serde_json::to_value(&self.fields).unwrap())
+    }
+}
+
+/// An ID without dashes, which the API accepts in both forms
+pub(crate) fn id_key(id: &str) -> String {
+    id.replace('-', "")
+}
+
+/// Whether two IDs refer to the same object, ignoring dashes
+pub(crate) fn same_id(a: &str, b: &str) -> bool {
+    id_key(a) == id_key(b)
+}
+
+/// Maximum page size accepted by Notion list endpoints
+pub const MAX_PAGE_SIZE: u32 = 100;
+
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct DatabaseQuery {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub filter: Option<Value>,
@@ -30,22 +322,137 @@ pub struct DatabaseQuery {
     pub start_cursor: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub page_size: Option<u32>,
+    /// Names or IDs of the only properties to return for each page.
+    /// Sent as `filter_properties` query parameters rather than in the body.
+    #[serde(skip)]
+    pub filter_properties: Vec<String>,
+}
+
+impl DatabaseQuery {
+    /// Creates an empty query that returns all pages in default order
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the filter object of the query
+    pub fn filter(mut self, filter: Value) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Appends a sort; sorts are applied in the order they are added
+    pub fn sort(mut self, sort: Sort) -> Self {
+        self.sorts.get_or_insert_with(Vec::new).push(sort);
+        self
+    }
+
+    /// Sets the cursor to continue a previous query from
+    pub fn start_cursor(mut self, cursor: &str) -> Self {
+        self.start_cursor = Some(cursor.to_string());
+        self
+    }
+
+    /// Sets the number of results per page (at most 100)
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = Some(page_size);
+        self
+    }
+
+    /// Restricts the properties returned for each page to the given names or IDs
+    pub fn filter_properties(mut self, properties: &[&str]) -> Self {
+        self.filter_properties
+            .extend(properties.iter().map(|p| p.to_string()));
+        self
+    }
+
+    /// Checks the query against the limits enforced by the Notion API
+    pub fn validate(&self) -> Result<(), NotionError> {
+        match self.page_size {
+            Some(0) => Err(NotionError::InvalidRequest(
+                "page_size must be greater than 0".to_string(),
+            )),
+            Some(size) if size > MAX_PAGE_SIZE => Err(NotionError::InvalidRequest(format!(
+                "page_size must be at most {}, got {}",
+                MAX_PAGE_SIZE, size
+            ))),
+            _ => Ok(()),
+        }
+    }
 }
 
-#[derive(Debug, Serialize)]
-pub struct Sort {
-    pub property: String,
-    pub direction: SortDirection,
+/// Sort criterion for a database query, either by property or by timestamp
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Sort {
+    Property {
+        property: String,
+        direction: SortDirection,
+    },
+    Timestamp {
+        timestamp: Timestamp,
+        direction: SortDirection,
+    },
 }
 
-#[derive(Debug, Serialize)]
+impl Sort {
+    /// Sorts by the given property name
+    pub fn property(property: &str, direction: SortDirection) -> Self {
+        Sort::Property {
+            property: property.to_string(),
+            direction,
+        }
+    }
+
+    /// Sorts by the page creation or last edit timestamp
+    pub fn timestamp(timestamp: Timestamp, direction: SortDirection) -> Self {
+        Sort::Timestamp {
+            timestamp,
+            direction,
+        }
+    }
+
+    /// Sorts by the given property in ascending order
+    pub fn ascending(property: &str) -> Self {
+        Self::property(property, SortDirection::Ascending)
+    }
+
+    /// Sorts by the given property in descending order
+    pub fn descending(property: &str) -> Self {
+        Self::property(property, SortDirection::Descending)
+    }
+
+    /// Sorts by creation time
+    pub fn created_time(direction: SortDirection) -> Self {
+        Self::timestamp(Timestamp::CreatedTime, direction)
+    }
+
+    /// Sorts by last edit time
+    pub fn last_edited_time(direction: SortDirection) -> Self {
+        Self::timestamp(Timestamp::LastEditedTime, direction)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum SortDirection {
     Ascending,
     Descending,
 }
 
+/// Page timestamps that can be used for sorting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Timestamp {
+    CreatedTime,
+    LastEditedTime,
+}
+
 impl Database {
+    /// Whether the database is archived or in the trash
+    pub fn is_archived(&self) -> bool {
+        self.archived || self.in_trash
+    }
+
     pub fn search_request(query: Option<&str>) -> RequestBuilder {
         let mut body = serde_json::json!({
             "filter": {
@@ -68,21 +475,117 @@ impl Database {
         Self::search_request(None)
     }
 
+    /// Looks up a property of the schema by ID or by name
+    pub fn find_property(&self, property: &PropertyRef) -> Option<&DatabaseProperty> {
+        match property {
+            PropertyRef::Name(name) => self.properties.get(name),
+            PropertyRef::Id(id) => self
+                .properties
+                .values()
+                .find(|p| p.id.as_deref() == Some(id.as_str())),
+        }
+    }
+
+    /// Resolves property names or IDs to property IDs, as required by `filter_properties`.
+    /// Names take precedence over IDs when both match.
+    pub fn resolve_property_ids(&self, properties: &[String]) -> Result<Vec<String>, NotionError> {
+        properties
+            .iter()
+            .map(|property| {
+                self.find_property(&PropertyRef::Name(property.clone()))
+                    .or_else(|| self.find_property(&PropertyRef::Id(property.clone())))
+                    .and_then(|p| p.id.clone())
+                    .ok_or_else(|| {
+                        NotionError::InvalidRequest(format!("Unknown property \"{}\"", property))
+                    })
+            })
+            .collect()
+    }
+
+    /// Resolves property updates against this schema into the `properties`
+    /// object of an update request, keyed by property ID.
+    ///
+    /// Several updates to the same property are merged; removing a property
+    /// cannot be combined with other updates to it.
+    pub fn resolve_property_updates(
+        &self,
+        updates: &[DatabasePropertyUpdate],
+    ) -> Result<Value, NotionError> {
+        let mut patch = serde_json::Map::new();
+
+        for update in updates {
+            let property = self.find_property(update.property()).ok_or_else(|| {
+                NotionError::InvalidRequest(format!("Unknown {}", update.property()))
+            })?;
+            let key = property.id.clone().unwrap_or_else(|| property.name.clone());
+            let conflict = || {
+                NotionError::InvalidRequest(format!(
+                    "Conflicting updates for property \"{}\"",
+                    property.name
+                ))
+            };
+
+            let entry = patch
+                .entry(key)
+                .or_insert_with(|| Value::Object(serde_json::Map::new()));
+            // Fields of the property set by earlier updates, which a removal
+            // or type change cannot be combined with
+            let pending = |entry: &Value| match entry {
+                Value::Object(fields) => Ok(fields.keys().any(|field| field != "name")),
+                _ => Err(conflict()),
+            };
+
+            match update {
+                DatabasePropertyUpdate::Remove { .. } => {
+                    if entry.as_object().is_some_and(|fields| !fields.is_empty()) {
+                        return Err(conflict());
+                    }
+                    *entry = Value::Null;
+                }
+                DatabasePropertyUpdate::Rename { new_name, .. } => {
+                    pending(entry)?;
+                    entry["name"] = Value::String(new_name.clone());
+                }
+                DatabasePropertyUpdate::ChangeType { schema, .. } => {
+                    if pending(entry)? {
+                        return Err(conflict());
+                    }
+                    entry[schema.type_name()] = schema.config();
+                }
+                DatabasePropertyUpdate::UpdateOptions { options, .. } => {
+                    let current = match &property.schema {
+                        PropertySchema::Select(config) | PropertySchema::MultiSelect(config) => {
+                            &config.options
+                        }
+                        _ => {
+                            return Err(NotionError::InvalidRequest(format!(
+                                "Options of {} property \"{}\" cannot be updated",
+                                property.schema.type_name(),
+                                property.name
+                            )))
+                        }
+                    };
+                    if pending(entry)? {
+                        return Err(conflict());
+                    }
+                    entry[property.schema.type_name()] =
+                        serde_json::json!({ "options": merge_select_options(current, options) });
+                }
+            }
+        }
+
+        Ok(Value::Object(patch))
+    }
+
     pub fn get_request(database_id: &str) -> RequestBuilder {
         RequestBuilder::new(&format!("/databases/{}", database_id))
     }
 
     pub fn create_request(parent_page_id: &str, title: &str, properties: Value) -> RequestBuilder {
-        RequestBuilder::new("/databases")
-            .method("POST")
-            .json_body(serde_json::json!({
-                "parent": { "type": "page_id", "page_id": parent_page_id },
-                "title": [{
-                    "type": "text",
-                    "text": { "content": title }
-                }],
-                "properties": properties
-            }))
+        CreateDatabase::new(parent_page_id)
+            .title(title)
+            .properties(properties)
+            .request()
     }
 
     pub fn update_request(
@@ -90,30 +593,31 @@ impl Database {
         title: Option<&str>,
         properties: Option<Value>,
     ) -> RequestBuilder {
-        let mut body = serde_json::Map::new();
-
-        if let Some(title_str) = title {
-            body.insert(
-                "title".to_string(),
-                serde_json::json!([{
-                    "type": "text",
-                    "text": { "content": title_str }
-                }]),
-            );
+        let mut update = UpdateDatabase::new();
+        if let Some(title) = title {
+            update = update.title(title);
         }
-
-        if let Some(props) = properties {
-            body.insert("properties".to_string(), props);
+        if let Some(properties) = properties {
+            update = update.properties(properties);
         }
+        update.request(database_id)
+    }
 
+    /// Request moving a database to the trash, or restoring it when `archived` is false
+    pub fn archive_request(database_id: &str, archived: bool) -> RequestBuilder {
         RequestBuilder::new(&format!("/databases/{}", database_id))
             .method("PATCH")
-            .json_body(serde_json::Value::Object(body))
+            .json_body(serde_json::json!({ "archived": archived }))
     }
 
+    /// Builds a query request; `filter_properties` are sent as given and must be IDs
     pub fn query_request(database_id: &str, query: DatabaseQuery) -> RequestBuilder {
-        RequestBuilder::new(&format!("/databases/{}/query", database_id))
+        let mut request = RequestBuilder::new(&format!("/databases/{}/query", database_id))
             .method("POST")
-            .json_body(serde_json::to_value(query).unwrap())
+            .json_body(serde_json::to_value(&query).unwrap());
+        for property_id in &query.filter_properties {
+            request = request.append_query_param("filter_properties", property_id);
+        }
+        request
     }
 }
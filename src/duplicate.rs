@@ -0,0 +1,362 @@
+use futures::TryStreamExt;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::client::NotionClient;
+use crate::copy::{CopyValues, SameWorkspace};
+use crate::database::{same_id, CreateDatabase, Database, DatabaseQuery, RichText};
+use crate::error::NotionError;
+use crate::page::Page;
+use crate::response::ObjectResponse;
+use crate::schema::{
+    is_read_only_type, DatabasePropertyUpdate, DualProperty, PropertyRef, PropertySchema,
+    RelationConfig, RelationKind,
+};
+
+/// Options for `NotionClient::duplicate_database`
+#[derive(Debug, Clone, Default)]
+pub struct DuplicateOptions {
+    /// Title of the copy. Defaults to the title of the source database.
+    pub title: Option<String>,
+    /// Also copy the block content of every page
+    pub copy_content: bool,
+    /// Keep two-way relations to other databases two-way. Notion then adds a
+    /// new property for the copy to the related database, so by default they
+    /// become one-way relations.
+    pub dual_external_relations: bool,
+}
+
+/// A database created by `NotionClient::duplicate_database`
+#[derive(Debug, Clone)]
+pub struct DuplicatedDatabase {
+    pub database: Database,
+    /// IDs of the source database, its pages and their copied blocks, mapped
+    /// to the IDs of their copies
+    pub ids: HashMap<String, String>,
+}
+
+/// Converts a property value of a page into a value that can be written to
+/// another page. `None` for computed properties, which cannot be written.
+///
+/// Options are referenced by name, since option IDs differ between
+/// databases. Files hosted by Notion are kept as `file` objects with their
+/// URL, which the API does not accept; they have to be uploaded again, or
+/// linked by their URL, which expires after an hour.
+pub fn writable_property_value(value: &Value) -> Option<Value> {
+    let kind = value["type"].as_str()?;
+    if is_read_only_type(kind) {
+        return None;
+    }
+    let content = &value[kind];
+    let each = |f: &dyn Fn(&Value) -> Value| -> Value {
+        content
+            .as_array()
+            .map(|items| items.iter().map(f).collect())
+            .unwrap_or_default()
+    };
+    let content = match kind {
+        "select" | "status" if !content.is_null() => json!({ "name": content["name"] }),
+        "multi_select" => each(&|option| json!({ "name": option["name"] })),
+        "people" | "relation" => each(&|item| json!({ "id": item["id"] })),
+        "files" => each(&|file| match file["type"].as_str() {
+            Some("file") => json!({
+                "name": file["name"],
+                "type": "file",
+                "file": { "url": file["file"]["url"] }
+            }),
+            _ => file.clone(),
+        }),
+        _ => content.clone(),
+    };
+    Some(json!({ kind: content }))
+}
+
+/// Relations of a database to itself, keyed by property name. Only one side
+/// of a two-way relation is kept, since Notion creates the other one.
+pub(crate) fn self_relations(source: &Database) -> BTreeMap<String, RelationKind> {
+    let mut relations = BTreeMap::new();
+    for (name, property) in &source.properties {
+        let PropertySchema::Relation(config) = &property.schema else {
+            continue;
+        };
+        if !same_id(&config.database_id, &source.id) {
+            continue;
+        }
+        if let RelationKind::Dual(dual) = &config.kind {
+            if dual
+                .synced_property_name
+                .as_ref()
+                .is_some_and(|synced| relations.contains_key(synced))
+            {
+                continue;
+            }
+        }
+        relations.insert(name.clone(), config.kind.clone());
+    }
+    relations
+}
+
+/// Property schemas adding the relations of `self_relations` to the copy
+pub(crate) fn self_relation_properties(
+    relations: &BTreeMap<String, RelationKind>,
+    database_id: &str,
+) -> Value {
+    relations
+        .iter()
+        .map(|(name, kind)| {
+            let kind = match kind {
+                RelationKind::Single => RelationKind::Single,
+                RelationKind::Dual(_) => RelationKind::Dual(DualProperty::default()),
+            };
+            let schema = PropertySchema::Relation(RelationConfig {
+                database_id: database_id.to_string(),
+                kind,
+            });
+            (name.clone(), schema.to_request_value())
+        })
+        .collect::<Map<String, Value>>()
+        .into()
+}
+
+/// Renames giving the other sides of two-way self-relations, which Notion
+/// added to the copy under names of its own, their original names
+pub(crate) fn self_relation_renames(
+    relations: &BTreeMap<String, RelationKind>,
+    copy: &Database,
+) -> Vec<DatabasePropertyUpdate> {
+    relations
+        .iter()
+        .filter_map(|(name, kind)| {
+            let RelationKind::Dual(dual) = kind else {
+                return None;
+            };
+            let synced = dual
+                .synced_property_name
+                .as_ref()
+                .filter(|synced| *synced != name)?;
+            let added = copy.properties.values().find(|property| {
+                matches!(
+                    &property.schema,
+                    PropertySchema::Relation(RelationConfig {
+                        kind: RelationKind::Dual(dual),
+                        ..
+                    }) if dual.synced_property_name.as_ref() == Some(name)
+                ) && &property.name != name
+            })?;
+            (&added.name != synced).then(|| {
+                DatabasePropertyUpdate::rename(PropertyRef::Name(added.name.clone()), synced)
+            })
+        })
+        .collect()
+}
+
+impl NotionClient {
+    /// Creates a copy of a database inside another page, with the same schema
+    /// and a copy of every page that is not archived.
+    ///
+    /// Relations from the database to itself point to the copy, and so do the
+    /// relation values between copied pages; relations to other databases
+    /// point to the same databases, one-way unless
+    /// `DuplicateOptions::dual_external_relations` is set. Status properties
+    /// get Notion's default options, since the API cannot set them. Values of
+    /// computed properties (formulas, rollups, timestamps, unique IDs) are
+    /// computed again by Notion, and files hosted by Notion are uploaded
+    /// again. Verification properties and property types this crate does not
+    /// model cannot be created through the API and are left out.
+    pub async fn duplicate_database(
+        &self,
+        source_id: &str,
+        target_parent_page_id: &str,
+        options: DuplicateOptions,
+    ) -> Result<DuplicatedDatabase, NotionError> {
+        let values = SameWorkspace { target: Some(self) };
+        self.duplicate_database_to(source_id, self, target_parent_page_id, options, &values)
+            .await
+    }
+
+    /// Duplicates a database into a page that `target` can access,
+    /// converting icons, covers, relations and property values with `values`.
+    /// Relation properties to databases that `values` does not map are left
+    /// out, along with the rollups through them, and so are the properties
+    /// that cannot be created.
+    pub(crate) async fn duplicate_database_to(
+        &self,
+        source_id: &str,
+        target: &NotionClient,
+        target_parent_page_id: &str,
+        options: DuplicateOptions,
+        values: &dyn CopyValues,
+    ) -> Result<DuplicatedDatabase, NotionError> {
+        let source = self.get_database(source_id).await?.data;
+        let is_self_relation = |schema: &PropertySchema| match schema {
+            PropertySchema::Relation(config) => same_id(&config.database_id, &source.id),
+            _ => false,
+        };
+
+        // Properties that depend on the new database (self-relations) or on
+        // other properties (rollups) are added once it exists
+        let mut create = CreateDatabase::new(target_parent_page_id)
+            .title_rich_text(match &options.title {
+                Some(title) => vec![RichText::text(title)],
+                None => source.title.clone(),
+            })
+            .description_rich_text(source.description.clone())
+            .is_inline(source.is_inline);
+        if let Some(icon) = &source.icon {
+            if let Some(icon) = values.icon(icon).await? {
+                create = create.icon(icon);
+            }
+        }
+        if let Some(cover) = &source.cover {
+            create = create.cover(values.cover(cover).await?);
+        }
+        let left_out: Vec<&String> = source
+            .properties
+            .iter()
+            .filter(|(_, property)| match &property.schema {
+                PropertySchema::Relation(config) => {
+                    values.related_database(&config.database_id).is_none()
+                }
+                PropertySchema::Verification | PropertySchema::Unknown { .. } => true,
+                _ => false,
+            })
+            .map(|(name, _)| name)
+            .collect();
+        let mut rollups = Map::new();
+        for (name, property) in &source.properties {
+            match &property.schema {
+                _ if left_out.contains(&name) => {}
+                schema if is_self_relation(schema) => {}
+                PropertySchema::Relation(config) => {
+                    if let Some(database_id) = values.related_database(&config.database_id) {
+                        let kind = match &config.kind {
+                            RelationKind::Dual(_) if options.dual_external_relations => {
+                                RelationKind::Dual(DualProperty::default())
+                            }
+                            _ => RelationKind::Single,
+                        };
+                        let schema = PropertySchema::Relation(RelationConfig { database_id, kind });
+                        create = create.property(name, &schema);
+                    }
+                }
+                // Status options and groups cannot be set through the API
+                PropertySchema::Status(_) => {
+                    create = create.properties(json!({ name: { "status": {} } }));
+                }
+                PropertySchema::Rollup(config) => {
+                    if config
+                        .relation_property_name
+                        .as_ref()
+                        .is_some_and(|relation| left_out.contains(&relation))
+                    {
+                        continue;
+                    }
+                    let mut config = config.clone();
+                    config.relation_property_id = None;
+                    config.rollup_property_id = None;
+                    rollups.insert(
+                        name.clone(),
+                        PropertySchema::Rollup(config).to_request_value(),
+                    );
+                }
+                schema => create = create.property(name, schema),
+            }
+        }
+        let database = target.create_database_with(create).await?.data;
+
+        let self_relations = self_relations(&source);
+        if !self_relations.is_empty() {
+            let updated = target
+                .update_database(
+                    &database.id,
+                    None,
+                    Some(self_relation_properties(&self_relations, &database.id)),
+                )
+                .await?
+                .data;
+            let renames = self_relation_renames(&self_relations, &updated);
+            if !renames.is_empty() {
+                target
+                    .update_database_properties(&database.id, &renames)
+                    .await?;
+            }
+        }
+        if !rollups.is_empty() {
+            target
+                .update_database(&database.id, None, Some(Value::Object(rollups)))
+                .await?;
+        }
+
+        let mut ids = HashMap::from([(source.id.clone(), database.id.clone())]);
+        let mut relations = Vec::new();
+        let pages = self.query_database_stream(&source.id, DatabaseQuery::new());
+        futures::pin_mut!(pages);
+        while let Some(mut page) = pages.try_next().await? {
+            self.complete_list_properties(&mut page).await?;
+            let mut properties = Map::new();
+            let mut related = Map::new();
+            for (name, value) in page.properties.as_object().into_iter().flatten() {
+                let is_self = source
+                    .properties
+                    .get(name)
+                    .is_some_and(|property| is_self_relation(&property.schema));
+                if left_out.contains(&name) {
+                    continue;
+                }
+                if !is_self {
+                    properties.extend(
+                        values
+                            .property(&page.id, name, value)
+                            .await?
+                            .map(|value| (name.clone(), value)),
+                    );
+                } else if self_relations.contains_key(name) {
+                    related.insert(name.clone(), value["relation"].clone());
+                }
+            }
+
+            let copy: ObjectResponse<Page> = target
+                .post(Page::create_in_database_request(
+                    &database.id,
+                    Value::Object(properties),
+                ))
+                .await?;
+            let copy_id = copy.data.id;
+            if options.copy_content {
+                ids.extend(
+                    self.copy_block_children_to(&page.id, target, &copy_id, values)
+                        .await?,
+                );
+            }
+            ids.insert(page.id, copy_id.clone());
+            relations.push((copy_id, related));
+        }
+
+        // Relations between copied pages can only be set once all of them exist
+        for (copy_id, related) in relations {
+            let properties: Map<String, Value> = related
+                .into_iter()
+                .filter_map(|(name, relation)| {
+                    let targets: Vec<Value> = relation
+                        .as_array()?
+                        .iter()
+                        .filter_map(|item| ids.get(item["id"].as_str()?))
+                        .map(|id| json!({ "id": id }))
+                        .collect();
+                    (!targets.is_empty()).then(|| (name, json!({ "relation": targets })))
+                })
+                .collect();
+            if !properties.is_empty() {
+                target
+                    .patch::<ObjectResponse<Page>>(Page::update_properties_request(
+                        &copy_id,
+                        Value::Object(properties),
+                    ))
+                    .await?;
+            }
+        }
+
+        let database = target.get_database(&database.id).await?.data;
+        Ok(DuplicatedDatabase { database, ids })
+    }
+}
@@ -14,6 +14,19 @@ pub enum NotionError {
     Unauthorized,
     /// Invalid request parameters
     InvalidRequest(String),
+    /// The page or database with this ID is already archived or in the trash
+    AlreadyArchived(String),
+    /// The page or database with this ID is not archived, so it cannot be restored
+    NotArchived(String),
+    /// A property value could not be converted to or from a Rust type
+    InvalidProperty {
+        /// Name of the property
+        property: String,
+        /// Description of the problem
+        message: String,
+    },
+    /// Reading or writing local data (files, CSV) failed
+    Io(std::io::Error),
     /// Notion API returned an error
     ApiError {
         /// Error code from Notion
@@ -36,6 +49,16 @@ impl fmt::Display for NotionError {
             }
             NotionError::Unauthorized => write!(f, "Authentication failed"),
             NotionError::InvalidRequest(msg) => write!(f, "Invalid request: {}", msg),
+            NotionError::AlreadyArchived(id) => write!(f, "Object {} is already archived", id),
+            NotionError::NotArchived(id) => write!(f, "Object {} is not archived", id),
+            NotionError::InvalidProperty { property, message } => {
+                write!(
+                    f,
+                    "Invalid value for property \"{}\": {}",
+                    property, message
+                )
+            }
+            NotionError::Io(e) => write!(f, "I/O error: {}", e),
             NotionError::ApiError { code, message } => {
                 write!(f, "Notion API error {}: {}", code, message)
             }
@@ -47,6 +70,7 @@ impl std::error::Error for NotionError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             NotionError::HttpError(e) => Some(e),
+            NotionError::Io(e) => Some(e),
             _ => None,
         }
     }
@@ -57,3 +81,15 @@ impl From<ReqwestError> for NotionError {
         NotionError::HttpError(error)
     }
 }
+
+impl From<std::io::Error> for NotionError {
+    fn from(error: std::io::Error) -> Self {
+        NotionError::Io(error)
+    }
+}
+
+impl From<csv::Error> for NotionError {
+    fn from(error: csv::Error) -> Self {
+        NotionError::Io(error.into())
+    }
+}
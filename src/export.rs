@@ -0,0 +1,310 @@
+use futures::TryStreamExt;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::Write;
+
+use crate::client::NotionClient;
+use crate::database::{Database, DatabaseQuery};
+use crate::error::NotionError;
+use crate::page::Page;
+use crate::schema::PropertySchema;
+
+/// How relation values are written to CSV
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RelationFormat {
+    /// Related page IDs
+    #[default]
+    Ids,
+    /// Titles of the related pages, fetched once per page and cached
+    Titles,
+}
+
+/// How people values are written to CSV
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PeopleFormat {
+    #[default]
+    Names,
+    /// Email addresses; bots and users without a visible email fall back to their name
+    Emails,
+}
+
+/// Options for `NotionClient::export_database_csv`
+#[derive(Debug, Clone)]
+pub struct CsvExportOptions {
+    /// Columns to write, in order. Defaults to the title property followed by
+    /// the other properties in alphabetical order.
+    pub columns: Option<Vec<String>>,
+    /// Field delimiter
+    pub delimiter: u8,
+    /// Separator between multiple values in one cell (multi-select, people, ...)
+    pub list_separator: String,
+    pub relations: RelationFormat,
+    pub people: PeopleFormat,
+    /// Filter and sorts applied to the exported pages
+    pub query: DatabaseQuery,
+}
+
+impl Default for CsvExportOptions {
+    fn default() -> Self {
+        Self {
+            columns: None,
+            delimiter: b',',
+            list_separator: ", ".to_string(),
+            relations: RelationFormat::default(),
+            people: PeopleFormat::default(),
+            query: DatabaseQuery::default(),
+        }
+    }
+}
+
+impl NotionClient {
+    /// Writes every page of a database as a CSV row, following query pagination.
+    /// Relation and people values are completed beyond the items included in
+    /// page objects. Returns the number of rows written, excluding the header.
+    pub async fn export_database_csv<W: Write>(
+        &self,
+        database_id: &str,
+        writer: W,
+        options: CsvExportOptions,
+    ) -> Result<usize, NotionError> {
+        let database = self.get_database(database_id).await?.data;
+        let columns = match &options.columns {
+            Some(columns) => {
+                if let Some(missing) = columns
+                    .iter()
+                    .find(|c| !database.properties.contains_key(*c))
+                {
+                    return Err(NotionError::InvalidRequest(format!(
+                        "Unknown property \"{}\"",
+                        missing
+                    )));
+                }
+                columns.clone()
+            }
+            None => default_columns(&database),
+        };
+
+        let mut csv = csv::WriterBuilder::new()
+            .delimiter(options.delimiter)
+            .from_writer(writer);
+        csv.write_record(&columns)?;
+
+        let mut titles = HashMap::new();
+        let mut rows = 0;
+        let pages = self.query_database_stream(database_id, options.query.clone());
+        futures::pin_mut!(pages);
+        while let Some(mut page) = pages.try_next().await? {
+            self.complete_list_properties(&mut page).await?;
+            if options.relations == RelationFormat::Titles {
+                self.fetch_relation_titles(&page, &columns, &mut titles)
+                    .await?;
+            }
+            csv.write_record(csv_record(&page, &columns, &options, &titles))?;
+            rows += 1;
+        }
+        csv.flush()?;
+        Ok(rows)
+    }
+
+    /// Fetches the titles of related pages that are not cached yet
+    async fn fetch_relation_titles(
+        &self,
+        page: &Page,
+        columns: &[String],
+        titles: &mut HashMap<String, String>,
+    ) -> Result<(), NotionError> {
+        for column in columns {
+            let Some(relations) = page.properties[column.as_str()]["relation"].as_array() else {
+                continue;
+            };
+            for relation in relations {
+                let Some(id) = relation["id"].as_str() else {
+                    continue;
+                };
+                if titles.contains_key(id) {
+                    continue;
+                }
+                let related = self.get_page(id).await?;
+                titles.insert(id.to_string(), page_title(&related.data));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The title property first, then every other property by name
+pub(crate) fn default_columns(database: &Database) -> Vec<String> {
+    let mut columns: Vec<String> = database
+        .properties
+        .values()
+        .filter(|p| p.schema == PropertySchema::Title)
+        .map(|p| p.name.clone())
+        .collect();
+    columns.extend(
+        database
+            .properties
+            .values()
+            .filter(|p| p.schema != PropertySchema::Title)
+            .map(|p| p.name.clone()),
+    );
+    columns
+}
+
+/// Plain text of the title property of a page
+pub fn page_title(page: &Page) -> String {
+    page.properties
+        .as_object()
+        .and_then(|properties| properties.values().find(|p| p["type"] == "title"))
+        .map(|title| rich_text_plain(&title["title"]))
+        .unwrap_or_default()
+}
+
+fn rich_text_plain(rich_text: &Value) -> String {
+    rich_text
+        .as_array()
+        .map(|segments| {
+            segments
+                .iter()
+                .filter_map(|segment| segment["plain_text"].as_str())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Items of a list-typed property value: option names, people names (or IDs),
+/// file URLs and related page IDs
+#[cfg(any(feature = "arrow", feature = "sqlite"))]
+pub(crate) fn list_items(value: &Value) -> Option<Vec<String>> {
+    let kind = value["type"].as_str()?;
+    let items = value[kind].as_array()?;
+    Some(
+        items
+            .iter()
+            .map(|item| {
+                let text = match kind {
+                    "multi_select" => &item["name"],
+                    "people" if item["name"].is_string() => &item["name"],
+                    "files" => {
+                        let file_type = item["type"].as_str().unwrap_or_default();
+                        &item[file_type]["url"]
+                    }
+                    _ => &item["id"],
+                };
+                text.as_str().unwrap_or_default().to_string()
+            })
+            .collect(),
+    )
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn date_range(date: &Value) -> String {
+    match (date["start"].as_str(), date["end"].as_str()) {
+        (Some(start), Some(end)) => format!("{}/{}", start, end),
+        (Some(start), None) => start.to_string(),
+        _ => String::new(),
+    }
+}
+
+fn user(value: &Value, people: PeopleFormat) -> String {
+    let name = value["name"].as_str().unwrap_or_default();
+    match (people, value["person"]["email"].as_str()) {
+        (PeopleFormat::Emails, Some(email)) => email.to_string(),
+        _ if !name.is_empty() => name.to_string(),
+        _ => scalar(&value["id"]),
+    }
+}
+
+/// The CSV cells of a page for the given columns
+pub(crate) fn csv_record(
+    page: &Page,
+    columns: &[String],
+    options: &CsvExportOptions,
+    relation_titles: &HashMap<String, String>,
+) -> Vec<String> {
+    columns
+        .iter()
+        .map(|column| {
+            let value = &page.properties[column.as_str()];
+            flatten_property_value(value, options, relation_titles)
+        })
+        .collect()
+}
+
+/// Flattens a page property value into a single CSV cell
+pub fn flatten_property_value(
+    value: &Value,
+    options: &CsvExportOptions,
+    relation_titles: &HashMap<String, String>,
+) -> String {
+    let kind = value["type"].as_str().unwrap_or_default();
+    let content = &value[kind];
+    let join = |items: Vec<String>| items.join(&options.list_separator);
+    let each = |f: &dyn Fn(&Value) -> String| -> Vec<String> {
+        content
+            .as_array()
+            .map(|items| items.iter().map(f).collect())
+            .unwrap_or_default()
+    };
+
+    match kind {
+        "title" | "rich_text" => rich_text_plain(content),
+        "number" | "checkbox" | "url" | "email" | "phone_number" | "created_time"
+        | "last_edited_time" => scalar(content),
+        "select" | "status" => scalar(&content["name"]),
+        "multi_select" => join(each(&|option| scalar(&option["name"]))),
+        "date" => date_range(content),
+        "people" => join(each(&|person| user(person, options.people))),
+        "created_by" | "last_edited_by" => user(content, options.people),
+        "files" => join(each(&|file| {
+            let kind = file["type"].as_str().unwrap_or_default();
+            scalar(&file[kind]["url"])
+        })),
+        "relation" => join(each(&|relation| {
+            let id = scalar(&relation["id"]);
+            match options.relations {
+                RelationFormat::Titles => relation_titles.get(&id).cloned().unwrap_or(id),
+                RelationFormat::Ids => id,
+            }
+        })),
+        "formula" => {
+            let kind = content["type"].as_str().unwrap_or_default();
+            match kind {
+                "date" => date_range(&content["date"]),
+                _ => scalar(&content[kind]),
+            }
+        }
+        "rollup" => {
+            let kind = content["type"].as_str().unwrap_or_default();
+            match kind {
+                "date" => date_range(&content["date"]),
+                "array" => join(
+                    content["array"]
+                        .as_array()
+                        .map(|items| {
+                            items
+                                .iter()
+                                .map(|item| flatten_property_value(item, options, relation_titles))
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                ),
+                _ => scalar(&content[kind]),
+            }
+        }
+        "unique_id" => match (content["prefix"].as_str(), &content["number"]) {
+            (_, Value::Null) => String::new(),
+            (Some(prefix), number) => format!("{}-{}", prefix, number),
+            (None, number) => number.to_string(),
+        },
+        "verification" => scalar(&content["state"]),
+        _ if value.is_null() => String::new(),
+        _ => content.to_string(),
+    }
+}
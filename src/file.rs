@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+
+/// A file referenced by a page or database, such as a cover image
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum File {
+    /// File hosted elsewhere and linked by URL
+    External { external: ExternalFile },
+    /// File uploaded to Notion; the URL expires after an hour
+    File { file: HostedFile },
+    /// File sent with the file upload API, referenced by upload ID when
+    /// attaching it to a page or block
+    FileUpload { file_upload: FileUploadRef },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExternalFile {
+    pub url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileUploadRef {
+    pub id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HostedFile {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiry_time: Option<String>,
+}
+
+impl File {
+    /// Links an externally hosted file
+    pub fn external(url: &str) -> Self {
+        File::External {
+            external: ExternalFile {
+                url: url.to_string(),
+            },
+        }
+    }
+
+    /// Attaches a file sent with the file upload API
+    pub fn uploaded(file_upload_id: &str) -> Self {
+        File::FileUpload {
+            file_upload: FileUploadRef {
+                id: file_upload_id.to_string(),
+            },
+        }
+    }
+
+    /// URL of the file, whichever way it is hosted. Empty for a file upload
+    /// that is not attached yet.
+    pub fn url(&self) -> &str {
+        match self {
+            File::External { external } => &external.url,
+            File::File { file } => &file.url,
+            File::FileUpload { .. } => "",
+        }
+    }
+
+    /// The file as it can be sent back to the API: uploaded files are linked
+    /// by their current URL, which expires after an hour
+    pub fn linked(&self) -> File {
+        match self {
+            File::File { file } => File::external(&file.url),
+            other => other.clone(),
+        }
+    }
+}
+
+/// Icon of a page or database
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Icon {
+    Emoji {
+        emoji: String,
+    },
+    External {
+        external: ExternalFile,
+    },
+    File {
+        file: HostedFile,
+    },
+    FileUpload {
+        file_upload: FileUploadRef,
+    },
+    CustomEmoji {
+        custom_emoji: CustomEmoji,
+    },
+    /// An icon type this crate does not model
+    #[serde(other)]
+    Unknown,
+}
+
+/// A custom emoji defined in the workspace
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomEmoji {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+impl Icon {
+    pub fn emoji(emoji: &str) -> Self {
+        Icon::Emoji {
+            emoji: emoji.to_string(),
+        }
+    }
+
+    pub fn external(url: &str) -> Self {
+        Icon::External {
+            external: ExternalFile {
+                url: url.to_string(),
+            },
+        }
+    }
+
+    /// Attaches an image sent with the file upload API
+    pub fn uploaded(file_upload_id: &str) -> Self {
+        Icon::FileUpload {
+            file_upload: FileUploadRef {
+                id: file_upload_id.to_string(),
+            },
+        }
+    }
+
+    /// The icon as it can be sent back to the API: uploaded files are linked
+    /// by their current URL, which expires after an hour. `None` for icon
+    /// types this crate does not model.
+    pub fn linked(&self) -> Option<Icon> {
+        match self {
+            Icon::File { file } => Some(Icon::external(&file.url)),
+            Icon::Unknown => None,
+            other => Some(other.clone()),
+        }
+    }
+
+    /// Uses a custom emoji of the workspace by ID
+    pub fn custom_emoji(id: &str) -> Self {
+        Icon::CustomEmoji {
+            custom_emoji: CustomEmoji {
+                id: id.to_string(),
+                name: None,
+                url: None,
+            },
+        }
+    }
+}
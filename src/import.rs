@@ -0,0 +1,500 @@
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::client::NotionClient;
+use crate::database::CreateDatabase;
+use crate::error::NotionError;
+use crate::page::Page;
+use crate::response::ObjectResponse;
+use crate::schema::PropertySchema;
+
+/// Maximum length of a single text object accepted by the Notion API
+const MAX_TEXT_LENGTH: usize = 2000;
+const MAX_FILE_NAME_LENGTH: usize = 100;
+
+/// Where imported rows are written
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ImportTarget {
+    /// An existing database whose property names match the CSV header
+    Database(String),
+    /// A new database created inside the given page from the inferred schema
+    CreateDatabase {
+        parent_page_id: String,
+        title: String,
+    },
+}
+
+/// Options for `NotionClient::import_database_csv`
+#[derive(Debug, Clone)]
+pub struct CsvImportOptions {
+    pub target: ImportTarget,
+    /// Field delimiter
+    pub delimiter: u8,
+    /// Separator between multiple values in one cell (multi-select, relation, ...)
+    pub list_separator: char,
+    /// Column holding page titles when creating a database. Defaults to the first column.
+    pub title_column: Option<String>,
+    /// Explicit property types for columns; other columns are inferred from their values
+    pub mapping: HashMap<String, PropertySchema>,
+    /// Number of pages created concurrently before the checkpoint is written
+    pub batch_size: usize,
+    /// File recording progress, so an interrupted import resumes where it
+    /// stopped and retries the rows that failed
+    pub checkpoint: Option<PathBuf>,
+}
+
+impl CsvImportOptions {
+    pub fn new(target: ImportTarget) -> Self {
+        Self {
+            target,
+            delimiter: b',',
+            list_separator: ',',
+            title_column: None,
+            mapping: HashMap::new(),
+            batch_size: 10,
+            checkpoint: None,
+        }
+    }
+}
+
+/// Progress reported after every batch
+#[derive(Debug, Clone, Default)]
+pub struct ImportProgress {
+    /// Rows handled so far, including rows an earlier run had imported
+    pub processed: usize,
+    pub total: usize,
+    pub created: usize,
+    pub failed: usize,
+}
+
+/// A row that could not be imported
+#[derive(Debug)]
+pub struct RowError {
+    /// Zero-based index of the row, excluding the header
+    pub row: usize,
+    pub error: NotionError,
+}
+
+/// Outcome of a CSV import
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub database_id: String,
+    pub created: usize,
+    /// Rows skipped because an earlier run had already imported them
+    pub resumed_from: usize,
+    /// Rows that failed in this run. Rows that failed in an earlier run are
+    /// retried, and only listed here when they fail again.
+    pub errors: Vec<RowError>,
+}
+
+/// Contents of the checkpoint file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Checkpoint {
+    /// Target of the import the checkpoint was written for
+    pub target: ImportTarget,
+    pub database_id: String,
+    /// Rows before this index have been attempted
+    pub completed_rows: usize,
+    /// Attempted rows that failed, which are retried when resuming
+    #[serde(default)]
+    pub failed_rows: Vec<usize>,
+}
+
+impl Checkpoint {
+    /// Reads the checkpoint of an import into `target`, if one was written.
+    /// Fails when the file belongs to an import into another target.
+    pub(crate) fn read(path: &Path, target: &ImportTarget) -> Result<Option<Self>, NotionError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(path)?;
+        let checkpoint = serde_json::from_str::<Checkpoint>(&contents)
+            .map_err(|e| NotionError::InvalidRequest(format!("Invalid checkpoint file: {}", e)))?;
+        if &checkpoint.target != target {
+            return Err(NotionError::InvalidRequest(format!(
+                "Checkpoint file {} was written for an import into {:?}",
+                path.display(),
+                checkpoint.target
+            )));
+        }
+        Ok(Some(checkpoint))
+    }
+
+    fn write(&self, path: &Path) -> Result<(), NotionError> {
+        std::fs::write(path, serde_json::to_string(self).unwrap())?;
+        Ok(())
+    }
+
+    /// Rows still to import out of `total`: the ones that failed, then the
+    /// ones not attempted yet
+    pub(crate) fn remaining_rows(&self, total: usize) -> Vec<usize> {
+        let mut failed: Vec<usize> = self
+            .failed_rows
+            .iter()
+            .copied()
+            .filter(|row| *row < total.min(self.completed_rows))
+            .collect();
+        failed.sort_unstable();
+        failed.dedup();
+        failed.extend(self.completed_rows..total);
+        failed
+    }
+}
+
+impl NotionClient {
+    /// Imports the rows of a CSV file as pages of a database.
+    ///
+    /// Pages are created in batches of `batch_size` concurrent requests, subject
+    /// to the client's rate limit. `progress` is called after each batch. Rows
+    /// that fail are collected in the report rather than aborting the import.
+    ///
+    /// With a checkpoint file, the database and the rows attempted are
+    /// recorded as the import goes. Running the import again with the same
+    /// target then retries the rows that failed and continues after the last
+    /// row attempted.
+    pub async fn import_database_csv<R: Read>(
+        &self,
+        reader: R,
+        options: CsvImportOptions,
+        mut progress: impl FnMut(&ImportProgress),
+    ) -> Result<ImportReport, NotionError> {
+        let mut csv = csv::ReaderBuilder::new()
+            .delimiter(options.delimiter)
+            .from_reader(reader);
+        let headers: Vec<String> = csv.headers()?.iter().map(str::to_string).collect();
+        let rows = csv.records().collect::<Result<Vec<_>, _>>()?;
+
+        let checkpoint = match &options.checkpoint {
+            Some(path) => Checkpoint::read(path, &options.target)?,
+            None => None,
+        };
+
+        let (mut checkpoint, schema) = match (checkpoint, &options.target) {
+            (Some(checkpoint), _) => {
+                let schema = self
+                    .column_schema(&checkpoint.database_id, &headers)
+                    .await?;
+                (checkpoint, schema)
+            }
+            (None, ImportTarget::Database(database_id)) => (
+                Checkpoint {
+                    target: options.target.clone(),
+                    database_id: database_id.clone(),
+                    completed_rows: 0,
+                    failed_rows: Vec::new(),
+                },
+                self.column_schema(database_id, &headers).await?,
+            ),
+            (
+                None,
+                ImportTarget::CreateDatabase {
+                    parent_page_id,
+                    title,
+                },
+            ) => {
+                let schema = infer_schema(&headers, &rows, &options);
+                let database = schema.iter().fold(
+                    CreateDatabase::new(parent_page_id).title(title),
+                    |database, (name, schema)| database.property(name, schema),
+                );
+                let created = self.create_database_with(database).await?;
+                let checkpoint = Checkpoint {
+                    target: options.target.clone(),
+                    database_id: created.data.id,
+                    completed_rows: 0,
+                    failed_rows: Vec::new(),
+                };
+                // Recorded right away, so that a rerun does not create another database
+                if let Some(path) = &options.checkpoint {
+                    checkpoint.write(path)?;
+                }
+                (checkpoint, schema)
+            }
+        };
+
+        let database_id = checkpoint.database_id.clone();
+        let remaining = checkpoint.remaining_rows(rows.len());
+        let resumed_from = rows.len() - remaining.len();
+        let mut report = ImportReport {
+            database_id: database_id.clone(),
+            resumed_from,
+            ..Default::default()
+        };
+        let mut status = ImportProgress {
+            processed: resumed_from,
+            total: rows.len(),
+            ..Default::default()
+        };
+
+        let batch_size = options.batch_size.max(1);
+        for (number, batch) in remaining.chunks(batch_size).enumerate() {
+            let results = join_all(batch.iter().map(|index| {
+                let database_id = &database_id;
+                let rows = &rows;
+                let schema = &schema;
+                let options = &options;
+                async move {
+                    let properties = row_properties(schema, &rows[*index], options.list_separator)?;
+                    let _: ObjectResponse<Page> = self
+                        .post(Page::create_in_database_request(database_id, properties))
+                        .await?;
+                    Ok::<_, NotionError>(())
+                }
+            }))
+            .await;
+
+            let mut failed = Vec::new();
+            for (index, result) in batch.iter().zip(results) {
+                match result {
+                    Ok(()) => status.created += 1,
+                    Err(error) => {
+                        status.failed += 1;
+                        failed.push(*index);
+                        report.errors.push(RowError { row: *index, error });
+                    }
+                }
+                status.processed += 1;
+            }
+
+            if let Some(path) = &options.checkpoint {
+                // Failed rows not retried yet stay recorded until they are
+                let retried = &remaining[..((number + 1) * batch_size).min(remaining.len())];
+                checkpoint.failed_rows.retain(|row| !retried.contains(row));
+                checkpoint.failed_rows.extend(failed);
+                checkpoint.completed_rows = checkpoint
+                    .completed_rows
+                    .max(batch.last().map_or(0, |row| row + 1));
+                checkpoint.write(path)?;
+            }
+            progress(&status);
+        }
+
+        report.created = status.created;
+        Ok(report)
+    }
+
+    /// Schema of the existing database properties matching each CSV column
+    async fn column_schema(
+        &self,
+        database_id: &str,
+        headers: &[String],
+    ) -> Result<Vec<(String, PropertySchema)>, NotionError> {
+        let database = self.get_database(database_id).await?.data;
+        headers
+            .iter()
+            .map(|header| {
+                database
+                    .properties
+                    .get(header)
+                    .map(|property| (header.clone(), property.schema.clone()))
+                    .ok_or_else(|| {
+                        NotionError::InvalidRequest(format!(
+                            "Column \"{}\" does not match any property of the database",
+                            header
+                        ))
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Determines the property type of every column from the mapping, the title
+/// column and the values of the column
+pub fn infer_schema(
+    headers: &[String],
+    rows: &[csv::StringRecord],
+    options: &CsvImportOptions,
+) -> Vec<(String, PropertySchema)> {
+    let title_column = options
+        .title_column
+        .clone()
+        .or_else(|| headers.first().cloned());
+
+    headers
+        .iter()
+        .enumerate()
+        .map(|(index, header)| {
+            let schema = if let Some(schema) = options.mapping.get(header) {
+                schema.clone()
+            } else if Some(header) == title_column.as_ref() {
+                PropertySchema::Title
+            } else {
+                infer_column(rows.iter().filter_map(|row| row.get(index)))
+            };
+            (header.clone(), schema)
+        })
+        .collect()
+}
+
+/// Infers the narrowest property type accepting every non-empty value
+pub fn infer_column<'a>(values: impl Iterator<Item = &'a str>) -> PropertySchema {
+    let values: Vec<&str> = values.map(str::trim).filter(|v| !v.is_empty()).collect();
+    let all =
+        |predicate: fn(&str) -> bool| !values.is_empty() && values.iter().all(|v| predicate(v));
+
+    if all(|v| parse_bool(v).is_some()) {
+        PropertySchema::Checkbox
+    } else if all(|v| v.parse::<f64>().is_ok()) {
+        PropertySchema::number("number")
+    } else if all(|v| parse_date_range(v).is_some()) {
+        PropertySchema::Date
+    } else if all(|v| v.starts_with("http://") || v.starts_with("https://")) {
+        PropertySchema::Url
+    } else if all(is_email) {
+        PropertySchema::Email
+    } else {
+        PropertySchema::RichText
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" => Some(true),
+        "false" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+fn is_email(value: &str) -> bool {
+    match value.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty() && domain.contains('.') && !value.contains(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+/// Whether the value starts with an ISO 8601 date (`YYYY-MM-DD`)
+fn is_iso_date(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    bytes.len() >= 10
+        && bytes[..10].iter().enumerate().all(|(i, b)| match i {
+            4 | 7 => *b == b'-',
+            _ => b.is_ascii_digit(),
+        })
+}
+
+/// Parses a date or an ISO 8601 interval (`start/end`), as written by the CSV export
+fn parse_date_range(value: &str) -> Option<(&str, Option<&str>)> {
+    match value.split_once('/') {
+        Some((start, end)) if is_iso_date(start) && is_iso_date(end) => Some((start, Some(end))),
+        None if is_iso_date(value) => Some((value, None)),
+        _ => None,
+    }
+}
+
+/// Name of an external file: the last segment of its URL, within the length limit of a file name
+pub(crate) fn file_name(url: &str) -> String {
+    let name = url.rsplit('/').next().unwrap_or(url);
+    name.chars().take(MAX_FILE_NAME_LENGTH).collect()
+}
+
+/// Text objects for a string, split to respect the length limit of a single object
+pub(crate) fn text_objects(value: &str) -> Value {
+    let chars: Vec<char> = value.chars().collect();
+    let segments: Vec<Value> = chars
+        .chunks(MAX_TEXT_LENGTH)
+        .map(|chunk| {
+            serde_json::json!({
+                "type": "text",
+                "text": { "content": chunk.iter().collect::<String>() }
+            })
+        })
+        .collect();
+    Value::Array(segments)
+}
+
+/// Converts a CSV cell into a page property value for the given schema.
+/// Returns `None` for empty cells and read-only properties.
+pub fn cell_to_property_value(
+    schema: &PropertySchema,
+    cell: &str,
+    list_separator: char,
+) -> Result<Option<Value>, String> {
+    let cell = cell.trim();
+    if cell.is_empty() || schema.is_read_only() {
+        return Ok(None);
+    }
+    let list = || -> Vec<&str> {
+        cell.split(list_separator)
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .collect()
+    };
+
+    let value = match schema {
+        PropertySchema::Title => serde_json::json!({ "title": text_objects(cell) }),
+        PropertySchema::RichText => serde_json::json!({ "rich_text": text_objects(cell) }),
+        PropertySchema::Number(_) => {
+            let number: f64 = cell
+                .parse()
+                .map_err(|_| format!("\"{}\" is not a number", cell))?;
+            serde_json::json!({ "number": number })
+        }
+        PropertySchema::Select(_) => serde_json::json!({ "select": { "name": cell } }),
+        PropertySchema::Status(_) => serde_json::json!({ "status": { "name": cell } }),
+        PropertySchema::MultiSelect(_) => {
+            let options: Vec<Value> = list()
+                .into_iter()
+                .map(|name| serde_json::json!({ "name": name }))
+                .collect();
+            serde_json::json!({ "multi_select": options })
+        }
+        PropertySchema::Date => {
+            let (start, end) =
+                parse_date_range(cell).ok_or_else(|| format!("\"{}\" is not a date", cell))?;
+            serde_json::json!({ "date": { "start": start, "end": end } })
+        }
+        PropertySchema::Checkbox => {
+            let checked =
+                parse_bool(cell).ok_or_else(|| format!("\"{}\" is not a boolean", cell))?;
+            serde_json::json!({ "checkbox": checked })
+        }
+        PropertySchema::Url => serde_json::json!({ "url": cell }),
+        PropertySchema::Email => serde_json::json!({ "email": cell }),
+        PropertySchema::PhoneNumber => serde_json::json!({ "phone_number": cell }),
+        PropertySchema::People | PropertySchema::Relation(_) => {
+            let ids: Vec<Value> = list()
+                .into_iter()
+                .map(|id| serde_json::json!({ "id": id }))
+                .collect();
+            serde_json::json!({ schema.type_name(): ids })
+        }
+        PropertySchema::Files => {
+            let files: Vec<Value> = list()
+                .into_iter()
+                .map(|url| {
+                    serde_json::json!({ "name": file_name(url), "type": "external", "external": { "url": url } })
+                })
+                .collect();
+            serde_json::json!({ "files": files })
+        }
+        other => {
+            return Err(format!(
+                "Importing {} properties is not supported",
+                other.type_name()
+            ))
+        }
+    };
+    Ok(Some(value))
+}
+
+fn row_properties(
+    schema: &[(String, PropertySchema)],
+    record: &csv::StringRecord,
+    list_separator: char,
+) -> Result<Value, NotionError> {
+    let mut properties = Map::new();
+    for ((name, property), cell) in schema.iter().zip(record.iter()) {
+        let value = cell_to_property_value(property, cell, list_separator)
+            .map_err(|e| NotionError::InvalidRequest(format!("Column \"{}\": {}", name, e)))?;
+        if let Some(value) = value {
+            properties.insert(name.clone(), value);
+        }
+    }
+    Ok(Value::Object(properties))
+}
@@ -1,14 +1,47 @@
+// Lets `#[derive(NotionRecord)]` refer to `::notion_rs` from inside this crate
+extern crate self as notion_rs;
+
+#[cfg(feature = "arrow")]
+pub mod arrow_export;
+pub mod block;
+pub mod bulk;
 pub mod client;
+pub mod codegen;
+pub mod copy;
 pub mod database;
+pub mod duplicate;
 pub mod error;
+pub mod export;
+pub mod file;
+pub mod import;
+pub mod migration;
+pub mod page;
+pub mod page_move;
+pub mod pagination;
+pub mod property;
+pub mod rate_limit;
+pub mod record;
+pub mod relation;
 pub mod request;
 pub mod response;
+pub mod schema;
+pub mod search;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod template;
+pub mod upload;
+pub mod upsert;
 
 // Re-export commonly used items
 pub use client::NotionClient;
 pub use database::Database;
 pub use error::NotionError;
+pub use notion_rs_derive::NotionRecord;
+pub use page::Page;
+pub use pagination::PaginatedStreamExt;
+pub use record::NotionRecord;
 pub use request::RequestBuilder;
+pub use schema::{DatabaseProperty, PropertySchema};
 
 #[cfg(test)]
 mod tests {
@@ -32,4 +65,1744 @@ mod tests {
         assert_eq!(url, "https://api.notion.com/v1/databases?page_size=100");
         assert!(builder.get_body().is_some());
     }
+
+    #[test]
+    fn test_database_query_builder() {
+        use crate::database::{DatabaseQuery, Sort, SortDirection};
+
+        let query = DatabaseQuery::new()
+            .filter(json!({ "property": "Done", "checkbox": { "equals": true } }))
+            .sort(Sort::descending("Priority"))
+            .sort(Sort::last_edited_time(SortDirection::Ascending))
+            .page_size(50);
+        assert!(query.validate().is_ok());
+
+        let body = serde_json::to_value(&query).unwrap();
+        assert_eq!(
+            body["sorts"],
+            json!([
+                { "property": "Priority", "direction": "descending" },
+                { "timestamp": "last_edited_time", "direction": "ascending" }
+            ])
+        );
+        assert_eq!(body["page_size"], 50);
+        assert!(body.get("start_cursor").is_none());
+
+        assert!(DatabaseQuery::new().page_size(101).validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_paginate_follows_cursors() {
+        use crate::pagination::paginate;
+        use crate::response::ListResponse;
+        use std::cell::RefCell;
+
+        let requested = RefCell::new(Vec::new());
+        let stream = paginate(|cursor: Option<String>| {
+            requested.borrow_mut().push(cursor.clone());
+            async move {
+                let (results, next_cursor) = match cursor.as_deref() {
+                    None => (vec![1, 2], Some("second".to_string())),
+                    Some("second") => (vec![3, 4], Some("third".to_string())),
+                    _ => (vec![5], None),
+                };
+                Ok(ListResponse {
+                    results,
+                    has_more: next_cursor.is_some(),
+                    next_cursor,
+                })
+            }
+        });
+        let limited = stream.collect_all(Some(3)).await.unwrap();
+        assert_eq!(limited, vec![1, 2, 3]);
+        // The third page is never requested once the maximum is reached
+        assert_eq!(*requested.borrow(), vec![None, Some("second".to_string())]);
+    }
+
+    #[test]
+    fn test_property_schema_round_trip() {
+        use crate::schema::{RelationKind, SelectOption};
+        use std::collections::BTreeMap;
+
+        let properties = json!({
+            "Name": { "id": "title", "name": "Name", "type": "title", "title": {} },
+            "Price": {
+                "id": "a%3Db",
+                "name": "Price",
+                "type": "number",
+                "number": { "format": "dollar" }
+            },
+            "Stage": {
+                "id": "s1",
+                "name": "Stage",
+                "type": "status",
+                "status": {
+                    "options": [{ "id": "o1", "name": "Done", "color": "green" }],
+                    "groups": [{ "id": "g1", "name": "Complete", "color": "green", "option_ids": ["o1"] }]
+                }
+            },
+            "Parent": {
+                "id": "r1",
+                "name": "Parent",
+                "type": "relation",
+                "relation": {
+                    "database_id": "db1",
+                    "type": "dual_property",
+                    "dual_property": { "synced_property_name": "Children", "synced_property_id": "r2" }
+                }
+            },
+            "Button": { "id": "b1", "name": "Button", "type": "button", "button": { "label": "Go" } }
+        });
+
+        let parsed: BTreeMap<String, DatabaseProperty> =
+            serde_json::from_value(properties.clone()).unwrap();
+        assert_eq!(parsed["Name"].schema, PropertySchema::Title);
+        assert_eq!(parsed["Price"].schema, PropertySchema::number("dollar"));
+        match &parsed["Stage"].schema {
+            PropertySchema::Status(config) => {
+                assert_eq!(config.options[0].name, SelectOption::new("Done").name);
+                assert_eq!(config.groups[0].option_ids, vec!["o1"]);
+            }
+            other => panic!("unexpected schema {:?}", other),
+        }
+        match &parsed["Parent"].schema {
+            PropertySchema::Relation(config) => {
+                assert!(matches!(config.kind, RelationKind::Dual(_)))
+            }
+            other => panic!("unexpected schema {:?}", other),
+        }
+        assert_eq!(parsed["Button"].schema.type_name(), "button");
+
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), properties);
+    }
+
+    #[test]
+    fn test_migration_plan() {
+        use crate::database::Database;
+        use crate::migration::{DesiredSchema, MigrationOptions, MigrationPlan, MigrationStep};
+
+        let database: Database = serde_json::from_value(json!({
+            "id": "db",
+            "title": [],
+            "url": "https://www.notion.so/db",
+            "created_time": "2024-01-01T00:00:00.000Z",
+            "last_edited_time": "2024-01-01T00:00:00.000Z",
+            "properties": {
+                "Title": { "id": "title", "name": "Title", "type": "title", "title": {} },
+                "Notes": { "id": "n", "name": "Notes", "type": "rich_text", "rich_text": {} },
+                "Tags": {
+                    "id": "t",
+                    "name": "Tags",
+                    "type": "multi_select",
+                    "multi_select": { "options": [{ "id": "o1", "name": "a", "color": "red" }] }
+                },
+                "Legacy": { "id": "l", "name": "Legacy", "type": "checkbox", "checkbox": {} }
+            }
+        }))
+        .unwrap();
+
+        let desired = DesiredSchema::new()
+            .property("Name", PropertySchema::Title)
+            .renamed_property("Description", "Notes", PropertySchema::RichText)
+            .property("Tags", PropertySchema::multi_select(&["a", "b"]))
+            .property("Estimate", PropertySchema::number("number"));
+
+        let plan = MigrationPlan::diff(&database, &desired);
+        let kinds: Vec<String> = plan.steps.iter().map(|step| step.to_string()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "remove property \"Legacy\" [destructive]",
+                "rename property \"Notes\" to \"Description\"",
+                "rename property \"Title\" to \"Name\"",
+                "add property \"Estimate\" (number)",
+                "add options to \"Tags\": b",
+            ]
+        );
+        assert!(plan.check(&MigrationOptions::default()).is_err());
+
+        match &plan.steps[4] {
+            step @ MigrationStep::AddSelectOptions { .. } => {
+                let options = &step.properties_patch()["t"]["multi_select"]["options"];
+                assert_eq!(options[0]["id"], "o1");
+                assert_eq!(options[1]["name"], "b");
+            }
+            other => panic!("unexpected step {:?}", other),
+        }
+        assert_eq!(plan.steps[0].properties_patch(), json!({ "l": null }));
+    }
+
+    #[test]
+    fn test_migration_plan_of_formula() {
+        use crate::database::Database;
+        use crate::migration::{DesiredSchema, MigrationPlan, MigrationStep};
+        use crate::schema::FormulaConfig;
+
+        let database: Database = serde_json::from_value(json!({
+            "id": "db",
+            "title": [],
+            "url": "https://www.notion.so/db",
+            "created_time": "2024-01-01T00:00:00.000Z",
+            "last_edited_time": "2024-01-01T00:00:00.000Z",
+            "properties": {
+                "Name": { "id": "title", "name": "Name", "type": "title", "title": {} },
+                "Total": { "id": "t", "name": "Total", "type": "formula", "formula": {
+                    "expression": "if(prop(\"Status\") == \"In progress\", prop(\"Price\") * 2, 0)"
+                } },
+                "Action": { "id": "a", "name": "Action", "type": "button", "button": { "id": "x" } }
+            }
+        }))
+        .unwrap();
+
+        let formula = |expression: &str| {
+            PropertySchema::Formula(FormulaConfig {
+                expression: expression.to_string(),
+            })
+        };
+        let button = PropertySchema::Unknown {
+            type_name: "button".to_string(),
+            config: json!({}),
+        };
+        // Notion reformats the declared expression and adds to configurations
+        let desired = DesiredSchema::new()
+            .property("Name", PropertySchema::Title)
+            .property(
+                "Total",
+                formula("if(prop(\"Status\")==\"In progress\",prop(\"Price\")*2,0)"),
+            )
+            .property("Action", button);
+        assert!(MigrationPlan::diff(&database, &desired).is_empty());
+
+        let desired = desired.property(
+            "Total",
+            formula("if(prop(\"Status\") == \"Inprogress\", prop(\"Price\") * 2, 0)"),
+        );
+        let plan = MigrationPlan::diff(&database, &desired);
+        assert!(matches!(
+            plan.steps.as_slice(),
+            [MigrationStep::UpdateConfig { name, .. }] if name == "Total"
+        ));
+    }
+
+    #[test]
+    fn test_migration_plan_of_own_schema() {
+        use crate::database::Database;
+        use crate::migration::{DesiredSchema, MigrationPlan, MigrationStep};
+        use crate::schema::{RollupConfig, UniqueIdConfig};
+
+        let database: Database = serde_json::from_value(json!({
+            "id": "db",
+            "title": [],
+            "url": "https://www.notion.so/db",
+            "created_time": "2024-01-01T00:00:00.000Z",
+            "last_edited_time": "2024-01-01T00:00:00.000Z",
+            "properties": {
+                "Name": { "id": "title", "name": "Name", "type": "title", "title": {} },
+                "Project": { "id": "p", "name": "Project", "type": "relation", "relation": {
+                    "database_id": "6f1c2b4e-0d6a-4c7e-9d8b-3a2f1e0c9b8a",
+                    "type": "dual_property",
+                    "dual_property": { "synced_property_name": "Tasks", "synced_property_id": "s" }
+                } },
+                "Owner": { "id": "o", "name": "Owner", "type": "relation", "relation": {
+                    "database_id": "people-db", "type": "single_property", "single_property": {}
+                } },
+                "Budget": { "id": "b", "name": "Budget", "type": "rollup", "rollup": {
+                    "relation_property_name": "Project", "relation_property_id": "p",
+                    "rollup_property_name": "Budget", "rollup_property_id": "x",
+                    "function": "sum"
+                } },
+                "ID": { "id": "i", "name": "ID", "type": "unique_id", "unique_id": { "prefix": "T" } },
+                "Estimate": { "id": "e", "name": "Estimate", "type": "number", "number": { "format": "number" } }
+            }
+        }))
+        .unwrap();
+
+        let rollup = |function: &str| {
+            PropertySchema::Rollup(RollupConfig {
+                relation_property_name: Some("Project".to_string()),
+                relation_property_id: None,
+                rollup_property_name: Some("Budget".to_string()),
+                rollup_property_id: None,
+                function: function.to_string(),
+            })
+        };
+        let desired = DesiredSchema::new()
+            .property("Name", PropertySchema::Title)
+            .property(
+                "Project",
+                PropertySchema::dual_relation("6f1c2b4e0d6a4c7e9d8b3a2f1e0c9b8a"),
+            )
+            .property("Owner", PropertySchema::relation("people-db"))
+            .property("Budget", rollup("sum"))
+            .property("ID", PropertySchema::UniqueId(UniqueIdConfig::default()))
+            .property("Estimate", PropertySchema::number("number"));
+        assert!(MigrationPlan::diff(&database, &desired).is_empty());
+
+        let desired = desired
+            .property("Budget", rollup("average"))
+            .property("Owner", PropertySchema::dual_relation("people-db"));
+        let plan = MigrationPlan::diff(&database, &desired);
+        assert_eq!(plan.steps.len(), 2);
+        assert!(plan
+            .steps
+            .iter()
+            .all(|step| matches!(step, MigrationStep::UpdateConfig { .. })));
+        assert!(plan.destructive_steps().next().is_none());
+    }
+
+    #[test]
+    fn test_archive_flags() {
+        use crate::page::Page;
+
+        let page: Page = serde_json::from_value(json!({
+            "id": "p",
+            "parent": { "type": "page_id", "page_id": "parent" },
+            "properties": {},
+            "in_trash": true
+        }))
+        .unwrap();
+        assert!(page.is_archived());
+
+        let request = Database::archive_request("db", false);
+        assert_eq!(request.get_method(), "PATCH");
+        assert_eq!(request.get_body(), Some(&json!({ "archived": false })));
+    }
+
+    #[test]
+    fn test_resolve_property_updates() {
+        use crate::schema::{DatabasePropertyUpdate, PropertyRef, SelectOption};
+
+        let database: Database = serde_json::from_value(json!({
+            "id": "db",
+            "title": [],
+            "url": "https://www.notion.so/db",
+            "created_time": "2024-01-01T00:00:00.000Z",
+            "last_edited_time": "2024-01-01T00:00:00.000Z",
+            "properties": {
+                "Priority": {
+                    "id": "p",
+                    "name": "Priority",
+                    "type": "select",
+                    "select": { "options": [{ "id": "o1", "name": "High", "color": "red" }] }
+                },
+                "Old": { "id": "x", "name": "Old", "type": "url", "url": {} }
+            }
+        }))
+        .unwrap();
+
+        let patch = database
+            .resolve_property_updates(&[
+                DatabasePropertyUpdate::rename(PropertyRef::name("Priority"), "Urgency"),
+                DatabasePropertyUpdate::update_options(
+                    PropertyRef::id("p"),
+                    vec![SelectOption::new("High"), SelectOption::new("Low")],
+                ),
+                DatabasePropertyUpdate::remove(PropertyRef::name("Old")),
+            ])
+            .unwrap();
+        assert_eq!(
+            patch,
+            json!({
+                "p": {
+                    "name": "Urgency",
+                    "select": {
+                        "options": [
+                            { "id": "o1", "name": "High", "color": "red" },
+                            { "name": "Low" }
+                        ]
+                    }
+                },
+                "x": null
+            })
+        );
+
+        assert!(database
+            .resolve_property_updates(&[
+                DatabasePropertyUpdate::remove(PropertyRef::id("x")),
+                DatabasePropertyUpdate::rename(PropertyRef::name("Old"), "New"),
+            ])
+            .is_err());
+        assert!(database
+            .resolve_property_updates(&[
+                DatabasePropertyUpdate::rename(PropertyRef::name("Old"), "New"),
+                DatabasePropertyUpdate::remove(PropertyRef::id("x")),
+            ])
+            .is_err());
+        assert!(database
+            .resolve_property_updates(&[DatabasePropertyUpdate::remove(PropertyRef::name(
+                "Missing"
+            ))])
+            .is_err());
+    }
+
+    #[test]
+    fn test_create_database_builder() {
+        use crate::database::{CreateDatabase, RichText};
+        use crate::file::{File, Icon};
+
+        let request = CreateDatabase::new("parent")
+            .title_rich_text(vec![
+                RichText::text("Tasks "),
+                RichText::link("docs", "https://example.com"),
+            ])
+            .description("Team backlog")
+            .icon(Icon::emoji("✅"))
+            .cover(File::external("https://example.com/cover.png"))
+            .is_inline(true)
+            .property("Name", &PropertySchema::Title)
+            .properties(json!({ "Done": { "checkbox": {} } }))
+            .request();
+        let body = request.get_body().unwrap();
+
+        assert_eq!(
+            body["parent"],
+            json!({ "type": "page_id", "page_id": "parent" })
+        );
+        assert_eq!(body["title"][0]["text"]["content"], "Tasks ");
+        assert_eq!(
+            body["title"][1]["text"]["link"]["url"],
+            "https://example.com"
+        );
+        assert_eq!(body["description"][0]["plain_text"], "Team backlog");
+        assert_eq!(body["icon"], json!({ "type": "emoji", "emoji": "✅" }));
+        assert_eq!(
+            body["cover"],
+            json!({ "type": "external", "external": { "url": "https://example.com/cover.png" } })
+        );
+        assert_eq!(body["is_inline"], true);
+        assert_eq!(
+            body["properties"],
+            json!({ "Name": { "title": {} }, "Done": { "checkbox": {} } })
+        );
+
+        let update = Database::update_request("db", Some("Renamed"), None);
+        assert_eq!(
+            update.get_body().unwrap()["title"][0]["text"]["content"],
+            "Renamed"
+        );
+        assert!(update.get_body().unwrap().get("properties").is_none());
+    }
+
+    #[test]
+    fn test_filter_properties() {
+        use crate::database::DatabaseQuery;
+
+        let database: Database = serde_json::from_value(json!({
+            "id": "db",
+            "title": [],
+            "url": "https://www.notion.so/db",
+            "created_time": "2024-01-01T00:00:00.000Z",
+            "last_edited_time": "2024-01-01T00:00:00.000Z",
+            "properties": {
+                "Name": { "id": "title", "name": "Name", "type": "title", "title": {} },
+                "Due": { "id": "d%3Ax", "name": "Due", "type": "date", "date": {} }
+            }
+        }))
+        .unwrap();
+
+        let ids = database
+            .resolve_property_ids(&["Due".to_string(), "title".to_string()])
+            .unwrap();
+        assert_eq!(ids, vec!["d%3Ax", "title"]);
+        assert!(database
+            .resolve_property_ids(&["Missing".to_string()])
+            .is_err());
+
+        let mut query = DatabaseQuery::new().page_size(10);
+        query.filter_properties = ids;
+        let request = Database::query_request("db", query);
+        assert_eq!(
+            request.build_url("https://api.notion.com/v1"),
+            "https://api.notion.com/v1/databases/db/query?filter_properties=d%3Ax&filter_properties=title"
+        );
+        assert!(request
+            .get_body()
+            .unwrap()
+            .get("filter_properties")
+            .is_none());
+    }
+
+    #[test]
+    fn test_flatten_property_values() {
+        use crate::export::{
+            flatten_property_value, CsvExportOptions, PeopleFormat, RelationFormat,
+        };
+        use std::collections::HashMap;
+
+        let options = CsvExportOptions {
+            list_separator: ";".to_string(),
+            people: PeopleFormat::Emails,
+            relations: RelationFormat::Titles,
+            ..Default::default()
+        };
+        let titles = HashMap::from([("r1".to_string(), "Invoice 1".to_string())]);
+        let flatten = |value: serde_json::Value| flatten_property_value(&value, &options, &titles);
+
+        assert_eq!(
+            flatten(
+                json!({ "type": "multi_select", "multi_select": [{ "name": "a" }, { "name": "b" }] })
+            ),
+            "a;b"
+        );
+        assert_eq!(
+            flatten(
+                json!({ "type": "date", "date": { "start": "2024-01-01", "end": "2024-01-31" } })
+            ),
+            "2024-01-01/2024-01-31"
+        );
+        assert_eq!(
+            flatten(json!({ "type": "people", "people": [
+                { "id": "u1", "name": "Ann", "person": { "email": "ann@example.com" } },
+                { "id": "u2", "name": "Bot" }
+            ] })),
+            "ann@example.com;Bot"
+        );
+        assert_eq!(
+            flatten(json!({ "type": "relation", "relation": [{ "id": "r1" }, { "id": "r2" }] })),
+            "Invoice 1;r2"
+        );
+        assert_eq!(flatten(json!({ "type": "number", "number": null })), "");
+        assert_eq!(
+            flatten(json!({ "type": "unique_id", "unique_id": { "prefix": "INV", "number": 7 } })),
+            "INV-7"
+        );
+        assert_eq!(
+            flatten(
+                json!({ "type": "rollup", "rollup": { "type": "array", "array": [
+                { "type": "number", "number": 1 },
+                { "type": "number", "number": 2.5 }
+            ] } })
+            ),
+            "1;2.5"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_csv_record_of_truncated_relation() {
+        use crate::export::{csv_record, CsvExportOptions};
+        use crate::relation::complete_list_properties_with;
+        use std::collections::HashMap;
+
+        let mut page: Page = serde_json::from_value(json!({
+            "id": "p",
+            "parent": { "database_id": "db" },
+            "properties": {
+                "Name": { "id": "title", "type": "title", "title": [{ "plain_text": "Order" }] },
+                "Items": { "id": "i", "type": "relation", "relation": [{ "id": "r0" }], "has_more": true }
+            }
+        }))
+        .unwrap();
+        let items: Vec<serde_json::Value> = (0..30)
+            .map(|i| json!({ "object": "property_item", "type": "relation", "relation": { "id": format!("r{}", i) } }))
+            .collect();
+        complete_list_properties_with(&mut page, |page_id, property_id| {
+            assert_eq!((page_id.as_str(), property_id.as_str()), ("p", "i"));
+            let items = items.clone();
+            async move { Ok(items) }
+        })
+        .await
+        .unwrap();
+
+        let options = CsvExportOptions {
+            list_separator: ";".to_string(),
+            ..Default::default()
+        };
+        let columns = vec!["Name".to_string(), "Items".to_string()];
+        let record = csv_record(&page, &columns, &options, &HashMap::new());
+        assert_eq!(record[0], "Order");
+        let ids: Vec<&str> = record[1].split(';').collect();
+        assert_eq!(ids.len(), 30);
+        assert_eq!(ids[29], "r29");
+    }
+
+    #[test]
+    fn test_import_checkpoint() {
+        use crate::import::{Checkpoint, ImportTarget};
+
+        let target = ImportTarget::CreateDatabase {
+            parent_page_id: "parent".to_string(),
+            title: "Expenses".to_string(),
+        };
+        let checkpoint = Checkpoint {
+            target: target.clone(),
+            database_id: "db".to_string(),
+            completed_rows: 6,
+            failed_rows: vec![4, 1],
+        };
+        assert_eq!(checkpoint.remaining_rows(8), vec![1, 4, 6, 7]);
+
+        let path =
+            std::env::temp_dir().join(format!("notion-checkpoint-{}.json", std::process::id()));
+        std::fs::write(&path, serde_json::to_string(&checkpoint).unwrap()).unwrap();
+        assert_eq!(Checkpoint::read(&path, &target).unwrap(), Some(checkpoint));
+        let other = Checkpoint::read(&path, &ImportTarget::Database("db".to_string()));
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(other, Err(NotionError::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn test_csv_import_inference() {
+        use crate::import::{cell_to_property_value, infer_schema, CsvImportOptions, ImportTarget};
+
+        let data = "Name,Amount,Paid,Due,Site\n\
+                    Rent,1200,yes,2024-01-01,https://example.com\n\
+                    Food,35.5,no,2024-01-02/2024-01-05,\n";
+        let mut reader = csv::Reader::from_reader(data.as_bytes());
+        let headers: Vec<String> = reader.headers().unwrap().iter().map(String::from).collect();
+        let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+
+        let mut options = CsvImportOptions::new(ImportTarget::Database("db".to_string()));
+        options
+            .mapping
+            .insert("Site".to_string(), PropertySchema::RichText);
+        let schema: Vec<String> = infer_schema(&headers, &rows, &options)
+            .iter()
+            .map(|(_, schema)| schema.type_name().to_string())
+            .collect();
+        assert_eq!(
+            schema,
+            vec!["title", "number", "checkbox", "date", "rich_text"]
+        );
+
+        assert_eq!(
+            cell_to_property_value(&PropertySchema::Date, "2024-01-02/2024-01-05", ',').unwrap(),
+            Some(json!({ "date": { "start": "2024-01-02", "end": "2024-01-05" } }))
+        );
+        assert_eq!(
+            cell_to_property_value(&PropertySchema::multi_select(&[]), "a, b", ',').unwrap(),
+            Some(json!({ "multi_select": [{ "name": "a" }, { "name": "b" }] }))
+        );
+        assert!(cell_to_property_value(&PropertySchema::number("number"), "abc", ',').is_err());
+        assert_eq!(
+            cell_to_property_value(&PropertySchema::Checkbox, "", ',').unwrap(),
+            None
+        );
+        let long = "x".repeat(2500);
+        let value = cell_to_property_value(&PropertySchema::RichText, &long, ',')
+            .unwrap()
+            .unwrap();
+        assert_eq!(value["rich_text"].as_array().unwrap().len(), 2);
+    }
+
+    #[derive(Debug, PartialEq, NotionRecord)]
+    struct Task {
+        #[notion(name = "Name", type = "title")]
+        name: String,
+        #[notion(name = "Due", type = "date")]
+        due: Option<String>,
+        #[notion(name = "Tags")]
+        tags: Vec<String>,
+        #[notion(name = "Points")]
+        points: Option<f64>,
+        done: bool,
+        #[notion(name = "Score", type = "formula", expression = "prop(\"Points\") * 2")]
+        score: Option<f64>,
+        #[notion(name = "Verified", type = "verification")]
+        verified: Option<String>,
+        #[notion(skip)]
+        cached: Option<String>,
+    }
+
+    #[test]
+    fn test_notion_record_derive() {
+        use crate::record::{NotionRecord as _, PropertyField};
+
+        let schema = Task::schema();
+        assert_eq!(schema["Name"], PropertySchema::Title);
+        assert_eq!(schema["Due"], PropertySchema::Date);
+        assert_eq!(schema["Tags"].type_name(), "multi_select");
+        assert_eq!(schema["done"], PropertySchema::Checkbox);
+        assert_eq!(
+            schema["Score"],
+            PropertySchema::formula("prop(\"Points\") * 2")
+        );
+        assert!(!schema.contains_key("cached"));
+
+        let task = Task {
+            name: "Write docs".to_string(),
+            due: None,
+            tags: vec!["docs".to_string()],
+            points: Some(3.0),
+            done: false,
+            score: None,
+            verified: Some("verified".to_string()),
+            cached: None,
+        };
+        let properties = task.to_properties().unwrap();
+        assert_eq!(
+            properties["Name"]["title"][0]["text"]["content"],
+            "Write docs"
+        );
+        assert_eq!(properties["Due"], json!({ "date": null }));
+        assert_eq!(
+            properties["Tags"],
+            json!({ "multi_select": [{ "name": "docs" }] })
+        );
+        assert!(properties.get("Score").is_none());
+        assert!(properties.get("Verified").is_none());
+
+        let page_properties = json!({
+            "Name": { "id": "title", "type": "title", "title": [{ "plain_text": "Write docs" }] },
+            "Due": { "id": "d", "type": "date", "date": { "start": "2024-05-01", "end": null } },
+            "Tags": { "id": "t", "type": "multi_select", "multi_select": [{ "id": "1", "name": "docs" }] },
+            "Points": { "id": "p", "type": "number", "number": null },
+            "done": { "id": "c", "type": "checkbox", "checkbox": true },
+            "Score": { "id": "s", "type": "formula", "formula": { "type": "number", "number": 6 } },
+            "Verified": { "id": "v", "type": "verification", "verification": { "state": "unverified", "verified_by": null, "date": null } }
+        });
+        let read = Task::from_properties(&page_properties).unwrap();
+        assert_eq!(read.due.as_deref(), Some("2024-05-01"));
+        assert_eq!(read.points, None);
+        assert!(read.done);
+        assert_eq!(read.score, Some(6.0));
+        assert_eq!(read.verified.as_deref(), Some("unverified"));
+
+        let url = json!({ "type": "url", "url": "https://example.com" });
+        assert_eq!(
+            String::from_property_value(&url).unwrap(),
+            "https://example.com"
+        );
+        let status = json!({ "type": "status", "status": { "id": "1", "name": "Done" } });
+        assert_eq!(String::from_property_value(&status).unwrap(), "Done");
+        let number = json!({ "type": "number", "number": 1 });
+        assert!(String::from_property_value(&number).is_err());
+
+        let long_url = format!("https://example.com/files/{}", "a".repeat(150));
+        let files = vec![long_url.clone()].to_property_value("files").unwrap();
+        assert_eq!(files[0]["name"], "a".repeat(100));
+        assert_eq!(files[0]["external"]["url"], long_url);
+
+        let missing = Task::from_properties(&json!({}));
+        assert!(matches!(missing, Err(NotionError::InvalidProperty { .. })));
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn test_pages_to_record_batch() {
+        use crate::arrow_export::{database_arrow_schema, pages_to_record_batch};
+        use crate::page::Page;
+        use arrow::array::{Array, Float64Array, ListArray, TimestampMillisecondArray};
+        use arrow::datatypes::DataType;
+
+        let database: Database = serde_json::from_value(json!({
+            "id": "db",
+            "title": [],
+            "url": "https://www.notion.so/db",
+            "created_time": "2024-01-01T00:00:00.000Z",
+            "last_edited_time": "2024-01-01T00:00:00.000Z",
+            "properties": {
+                "Name": { "id": "title", "name": "Name", "type": "title", "title": {} },
+                "Amount": { "id": "a", "name": "Amount", "type": "number", "number": { "format": "yen" } },
+                "Due": { "id": "d", "name": "Due", "type": "date", "date": {} },
+                "Tags": { "id": "t", "name": "Tags", "type": "multi_select", "multi_select": { "options": [] } },
+                "id": { "id": "n", "name": "id", "type": "number", "number": {} }
+            }
+        }))
+        .unwrap();
+        let schema = database_arrow_schema(&database);
+        let names: Vec<&str> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["id", "Name", "Amount", "Due", "Tags", "property_id"]
+        );
+        assert_eq!(schema.field(2).data_type(), &DataType::Float64);
+        assert_eq!(schema.field(5).data_type(), &DataType::Float64);
+
+        let page = |id: &str, amount: serde_json::Value, due: serde_json::Value| -> Page {
+            serde_json::from_value(json!({
+                "id": id,
+                "parent": { "database_id": "db" },
+                "properties": {
+                    "Name": { "type": "title", "title": [{ "plain_text": id }] },
+                    "Amount": { "type": "number", "number": amount },
+                    "Due": { "type": "date", "date": due },
+                    "Tags": { "type": "multi_select", "multi_select": [{ "name": "a" }, { "name": "b" }] },
+                    "id": { "type": "number", "number": 7 }
+                }
+            }))
+            .unwrap()
+        };
+        let pages = vec![
+            page("p1", json!(12.5), json!({ "start": "2024-01-02" })),
+            page("p2", json!(null), json!(null)),
+        ];
+        let batch = pages_to_record_batch(schema, &pages).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+
+        let amounts = batch
+            .column(2)
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+        assert_eq!(amounts.value(0), 12.5);
+        assert!(amounts.is_null(1));
+        let due = batch
+            .column(3)
+            .as_any()
+            .downcast_ref::<TimestampMillisecondArray>()
+            .unwrap();
+        assert_eq!(due.value(0), 1_704_153_600_000);
+        assert!(due.is_null(1));
+        let tags = batch
+            .column(4)
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .unwrap();
+        assert_eq!(tags.value(0).len(), 2);
+        let ids = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<arrow::array::StringArray>()
+            .unwrap();
+        assert_eq!(ids.value(0), "p1");
+        let numbers = batch
+            .column(5)
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+        assert_eq!(numbers.value(1), 7.0);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_mirror_write_pages() {
+        use crate::sqlite::SqliteMirror;
+
+        let mut database: Database = serde_json::from_value(json!({
+            "id": "db",
+            "title": [{ "plain_text": "Tasks", "text": { "content": "Tasks" } }],
+            "url": "https://www.notion.so/db",
+            "created_time": "2024-01-01T00:00:00.000Z",
+            "last_edited_time": "2024-01-01T00:00:00.000Z",
+            "properties": {
+                "Name": { "id": "title", "name": "Name", "type": "title", "title": {} },
+                "Hours": { "id": "h", "name": "Hours", "type": "number", "number": {} },
+                "ID": { "id": "i", "name": "ID", "type": "unique_id", "unique_id": { "prefix": "T" } },
+                "Tags": { "id": "t", "name": "Tags", "type": "multi_select", "multi_select": { "options": [] } },
+                "Blocked by": { "id": "b", "name": "Blocked by", "type": "relation",
+                    "relation": { "database_id": "db", "type": "single_property", "single_property": {} } }
+            }
+        }))
+        .unwrap();
+        let page = |id: &str, edited: &str, blocked_by: serde_json::Value| -> Page {
+            serde_json::from_value(json!({
+                "id": id,
+                "parent": { "database_id": "db" },
+                "created_time": "2024-01-01T00:00:00.000Z",
+                "last_edited_time": edited,
+                "properties": {
+                    "Name": { "type": "title", "title": [{ "plain_text": id }] },
+                    "Hours": { "type": "number", "number": 2.5 },
+                    "ID": { "type": "unique_id", "unique_id": { "prefix": "T", "number": 7 } },
+                    "Tags": { "type": "multi_select", "multi_select": [{ "name": "a" }] },
+                    "Blocked by": { "type": "relation", "relation": blocked_by }
+                }
+            }))
+            .unwrap()
+        };
+
+        let mut mirror = SqliteMirror::in_memory().unwrap();
+        let pages = vec![
+            page("p1", "2024-01-02T00:00:00.000Z", json!([{ "id": "p2" }])),
+            page("p2", "2024-01-03T00:00:00.000Z", json!([])),
+        ];
+        let table = mirror.write_pages(&database, &pages).unwrap();
+        assert_eq!(table, "tasks");
+        assert_eq!(
+            mirror.last_edited_time("db").unwrap().as_deref(),
+            Some("2024-01-03T00:00:00.000Z")
+        );
+
+        let (name, hours, tags): (String, f64, String) = mirror
+            .connection()
+            .query_row(
+                "SELECT Name, Hours, Tags FROM tasks WHERE id = 'p1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            (name.as_str(), hours, tags.as_str()),
+            ("p1", 2.5, "[\"a\"]")
+        );
+        let blocked: String = mirror
+            .connection()
+            .query_row("SELECT related_page_id FROM tasks__blocked_by", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(blocked, "p2");
+        let unique_id: String = mirror
+            .connection()
+            .query_row("SELECT property_ID FROM tasks WHERE id = 'p1'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(unique_id, "T-7");
+
+        // A new property adds a column; re-writing a page replaces its relations
+        database.properties.insert(
+            "Done".to_string(),
+            serde_json::from_value(
+                json!({ "id": "d", "name": "Done", "type": "checkbox", "checkbox": {} }),
+            )
+            .unwrap(),
+        );
+        mirror
+            .write_pages(
+                &database,
+                &[page("p1", "2024-01-01T00:00:00.000Z", json!([]))],
+            )
+            .unwrap();
+        let links: i64 = mirror
+            .connection()
+            .query_row("SELECT COUNT(*) FROM tasks__blocked_by", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(links, 0);
+        mirror
+            .connection()
+            .prepare("SELECT Done FROM tasks")
+            .unwrap();
+        assert_eq!(
+            mirror.last_edited_time("db").unwrap().as_deref(),
+            Some("2024-01-03T00:00:00.000Z")
+        );
+    }
+
+    #[test]
+    fn test_relation_graph() {
+        use crate::relation::{relation_ids, RelationEdge, RelationGraph};
+
+        let page: Page = serde_json::from_value(json!({
+            "id": "a",
+            "parent": { "database_id": "db" },
+            "properties": {
+                "Name": { "id": "title", "type": "title", "title": [] },
+                "Parent": { "id": "p", "type": "relation", "relation": [{ "id": "b" }], "has_more": false },
+                "Children": { "id": "c", "type": "relation", "relation": [] }
+            }
+        }))
+        .unwrap();
+        let ids = relation_ids(&page);
+        assert_eq!(ids.len(), 2);
+        assert_eq!(ids["Parent"], vec!["b".to_string()]);
+        assert!(ids["Children"].is_empty());
+
+        let mut other = page.clone();
+        other.id = "b".to_string();
+        let graph = RelationGraph {
+            root: "a".to_string(),
+            pages: [("a".to_string(), page), ("b".to_string(), other)].into(),
+            edges: vec![
+                RelationEdge {
+                    from: "a".into(),
+                    property: "Parent".into(),
+                    to: "b".into(),
+                },
+                RelationEdge {
+                    from: "b".into(),
+                    property: "Parent".into(),
+                    to: "a".into(),
+                },
+                RelationEdge {
+                    from: "b".into(),
+                    property: "Parent".into(),
+                    to: "c".into(),
+                },
+            ],
+        };
+        let related: Vec<&str> = graph
+            .related("b", "Parent")
+            .iter()
+            .map(|p| p.id.as_str())
+            .collect();
+        assert_eq!(related, vec!["a"]);
+    }
+
+    #[test]
+    fn test_property_value_matches() {
+        use crate::upsert::property_value_matches;
+
+        let actual = json!({
+            "id": "x",
+            "type": "rich_text",
+            "rich_text": [{
+                "type": "text",
+                "text": { "content": "hello", "link": null },
+                "annotations": { "bold": false },
+                "plain_text": "hello"
+            }]
+        });
+        assert!(property_value_matches(
+            &json!({ "rich_text": [{ "text": { "content": "hello" } }] }),
+            &actual
+        ));
+        assert!(!property_value_matches(
+            &json!({ "rich_text": [{ "text": { "content": "bye" } }] }),
+            &actual
+        ));
+        assert!(!property_value_matches(
+            &json!({ "rich_text": [] }),
+            &actual
+        ));
+        assert!(property_value_matches(
+            &json!({ "number": 3 }),
+            &json!({ "type": "number", "number": 3.0 })
+        ));
+        assert!(property_value_matches(
+            &json!({ "date": { "start": "2024-01-01" } }),
+            &json!({ "date": { "start": "2024-01-01", "end": null, "time_zone": null } })
+        ));
+        assert!(!property_value_matches(
+            &json!({ "select": { "name": "A" } }),
+            &json!({ "select": null })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_bulk_apply_to_pages() {
+        use crate::bulk::{apply_to_pages, BulkAction, BulkOptions, BulkPageResult};
+        use std::cell::RefCell;
+
+        assert_eq!(BulkOptions::default().concurrency, 3);
+        let page = |id: &str, title: &str| -> Page {
+            serde_json::from_value(json!({
+                "object": "page",
+                "id": id,
+                "parent": { "type": "database_id", "database_id": "db" },
+                "url": format!("https://www.notion.so/{}", id),
+                "created_time": "2024-01-01T00:00:00.000Z",
+                "last_edited_time": "2024-01-01T00:00:00.000Z",
+                "properties": {
+                    "Name": { "id": "title", "type": "title", "title": [{ "plain_text": title }] }
+                }
+            }))
+            .unwrap()
+        };
+        let pages = vec![page("a", "First"), page("b", "Second"), page("c", "Third")];
+        let ids = |pages: Vec<&BulkPageResult>| -> Vec<String> {
+            pages.iter().map(|p| p.page_id.clone()).collect()
+        };
+
+        // A dry run lists the matching pages without sending anything
+        let dry_run = BulkOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        let report = apply_to_pages(&pages, &dry_run, &BulkAction::Archive, |_| async {
+            panic!("nothing is sent on a dry run")
+        })
+        .await;
+        assert!(report.dry_run);
+        assert_eq!(ids(report.succeeded().collect()), vec!["a", "b", "c"]);
+        let titles: Vec<&str> = report.pages.iter().map(|p| p.title.as_str()).collect();
+        assert_eq!(titles, vec!["First", "Second", "Third"]);
+
+        let sent = RefCell::new(Vec::new());
+        let send = |request: crate::request::RequestBuilder| {
+            let url = request.build_url("https://api.notion.com/v1");
+            sent.borrow_mut().push((
+                request.get_method().to_string(),
+                url.clone(),
+                request.get_body().cloned(),
+            ));
+            async move {
+                if url.ends_with("/b") {
+                    Err(NotionError::ApiError {
+                        code: "validation_error".to_string(),
+                        message: "Status is not a property".to_string(),
+                    })
+                } else {
+                    Ok(())
+                }
+            }
+        };
+        let patch = json!({ "Status": { "select": { "name": "Done" } } });
+        let report = apply_to_pages(
+            &pages,
+            &BulkOptions::default(),
+            &BulkAction::Update(patch.clone()),
+            send,
+        )
+        .await;
+        assert!(!report.dry_run);
+        assert_eq!(ids(report.succeeded().collect()), vec!["a", "c"]);
+        assert_eq!(ids(report.failed().collect()), vec!["b"]);
+        let sent = sent.take();
+        assert_eq!(sent.len(), 3);
+        assert_eq!(sent[0].0, "PATCH");
+        assert_eq!(sent[0].1, "https://api.notion.com/v1/pages/a");
+        assert_eq!(sent[0].2, Some(json!({ "properties": patch })));
+
+        let archive = BulkAction::Archive.request("c");
+        assert_eq!(archive.get_method(), "PATCH");
+        assert_eq!(
+            archive.build_url("https://api.notion.com/v1"),
+            "https://api.notion.com/v1/pages/c"
+        );
+        assert_eq!(archive.get_body(), Some(&json!({ "archived": true })));
+    }
+
+    #[test]
+    fn test_block_request_value() {
+        use crate::block::Block;
+
+        let image: Block = serde_json::from_value(json!({
+            "object": "block",
+            "id": "b1",
+            "type": "image",
+            "has_children": false,
+            "image": {
+                "caption": [],
+                "type": "file",
+                "file": { "url": "https://x/a.png", "expiry_time": "t" }
+            }
+        }))
+        .unwrap();
+        // Hosted files are kept for uploading, without their expiry time
+        assert_eq!(
+            image.to_request_value(),
+            Some(json!({
+                "object": "block",
+                "type": "image",
+                "image": { "caption": [], "type": "file", "file": { "url": "https://x/a.png" } }
+            }))
+        );
+
+        let child: Block = serde_json::from_value(json!({
+            "id": "b2", "type": "child_page", "child_page": { "title": "Child" }
+        }))
+        .unwrap();
+        assert_eq!(child.to_request_value(), None);
+    }
+
+    #[test]
+    fn test_writable_property_value() {
+        use crate::duplicate::writable_property_value;
+
+        assert_eq!(
+            writable_property_value(&json!({
+                "id": "s", "type": "select", "select": { "id": "abc", "name": "Done", "color": "green" }
+            })),
+            Some(json!({ "select": { "name": "Done" } }))
+        );
+        assert_eq!(
+            writable_property_value(&json!({ "type": "select", "select": null })),
+            Some(json!({ "select": null }))
+        );
+        assert_eq!(
+            writable_property_value(&json!({
+                "type": "files",
+                "files": [{ "name": "a.png", "type": "file", "file": { "url": "https://x/a.png", "expiry_time": "t" } }]
+            })),
+            Some(json!({
+                "files": [{ "name": "a.png", "type": "file", "file": { "url": "https://x/a.png" } }]
+            }))
+        );
+
+        // Hosted files are only linked by their expiring URL on request
+        let mut files = writable_property_value(&json!({
+            "type": "files",
+            "files": [{ "name": "a.png", "type": "file", "file": { "url": "https://x/a.png" } }]
+        }))
+        .unwrap();
+        crate::upload::link_hosted_files(&mut files);
+        assert_eq!(
+            files,
+            json!({
+                "files": [{ "name": "a.png", "type": "external", "external": { "url": "https://x/a.png" } }]
+            })
+        );
+        assert_eq!(
+            writable_property_value(
+                &json!({ "type": "formula", "formula": { "type": "number", "number": 1 } })
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_self_relations() {
+        use crate::duplicate::{self_relation_properties, self_relation_renames, self_relations};
+        use crate::schema::{DatabasePropertyUpdate, PropertyRef};
+
+        let dual = |database_id: &str, synced: &str| {
+            json!({ "relation": {
+                "database_id": database_id,
+                "type": "dual_property",
+                "dual_property": { "synced_property_name": synced, "synced_property_id": "x" }
+            } })
+        };
+        let database = |id: &str, relations: serde_json::Value| -> Database {
+            let mut properties = json!({
+                "Name": { "id": "title", "type": "title", "title": {} }
+            });
+            for (name, relation) in relations.as_object().unwrap() {
+                let mut property = relation.clone();
+                property["name"] = json!(name);
+                property["type"] = json!("relation");
+                properties[name] = property;
+            }
+            serde_json::from_value(json!({
+                "id": id,
+                "title": [],
+                "url": "https://www.notion.so/db",
+                "created_time": "2024-01-01T00:00:00.000Z",
+                "last_edited_time": "2024-01-01T00:00:00.000Z",
+                "properties": properties
+            }))
+            .unwrap()
+        };
+
+        let source = database(
+            "1234-abcd",
+            json!({
+                "Parent": dual("1234abcd", "Children"),
+                "Children": dual("1234-abcd", "Parent"),
+                "See also": { "relation": {
+                    "database_id": "1234-abcd", "type": "single_property", "single_property": {}
+                } },
+                "Client": dual("clients", "Projects")
+            }),
+        );
+        let relations = self_relations(&source);
+        assert_eq!(
+            relations.keys().collect::<Vec<_>>(),
+            vec!["Children", "See also"]
+        );
+        assert_eq!(
+            self_relation_properties(&relations, "copy"),
+            json!({
+                "Children": { "relation": {
+                    "database_id": "copy", "type": "dual_property", "dual_property": {}
+                } },
+                "See also": { "relation": {
+                    "database_id": "copy", "type": "single_property", "single_property": {}
+                } }
+            })
+        );
+
+        let copy = database(
+            "copy",
+            json!({
+                "Children": dual("copy", "Related to copy (Children)"),
+                "Related to copy (Children)": dual("copy", "Children")
+            }),
+        );
+        assert_eq!(
+            self_relation_renames(&relations, &copy),
+            vec![DatabasePropertyUpdate::rename(
+                PropertyRef::Name("Related to copy (Children)".to_string()),
+                "Parent"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_generate_record() {
+        use crate::codegen::{generate_record, CodegenOptions};
+
+        let database: Database = serde_json::from_value(json!({
+            "id": "db",
+            "title": [{ "plain_text": "Sprint tasks", "text": { "content": "Sprint tasks" } }],
+            "url": "https://www.notion.so/db",
+            "created_time": "2024-01-01T00:00:00.000Z",
+            "last_edited_time": "2024-01-01T00:00:00.000Z",
+            "properties": {
+                "Name": { "id": "title", "name": "Name", "type": "title", "title": {} },
+                "Status": { "id": "s", "name": "Status", "type": "status", "status": {
+                    "options": [{ "name": "Not started" }, { "name": "Done" }], "groups": []
+                } },
+                "Type": { "id": "t", "name": "Type", "type": "select", "select": { "options": [] } },
+                "Estimate (h)": { "id": "e", "name": "Estimate (h)", "type": "number", "number": {} },
+                "Button": { "id": "b", "name": "Button", "type": "button", "button": {} },
+                "Self": { "id": "f", "name": "Self", "type": "rich_text", "rich_text": {} },
+                "Box": { "id": "x", "name": "Box", "type": "select", "select": {
+                    "options": [{ "name": "Self" }, { "name": "crate" }]
+                } }
+            }
+        }))
+        .unwrap();
+        let source = generate_record(&database, &CodegenOptions::default());
+        syn::parse_file(&source).unwrap();
+        assert!(source.contains("pub struct SprintTasks {"));
+        assert!(source.contains("pub enum SprintTasksStatus {"));
+        assert!(
+            source.contains("    #[serde(rename = \"Not started\")]\n    NotStarted,\n    Done,\n")
+        );
+        assert!(source.contains("    #[notion(name = \"Status\", type = \"status\")]\n    pub status: Option<SprintTasksStatus>,"));
+        assert!(source.contains(
+            "    #[notion(name = \"Type\", type = \"select\")]\n    pub type_: Option<String>,"
+        ));
+        assert!(source.contains("    pub estimate_h: Option<f64>,"));
+        assert!(source.contains("// \"Button\" is skipped"));
+        assert!(source.contains("    pub self_: Option<String>,"));
+        assert!(source.contains("    pub box_: Option<SprintTasksBox>,"));
+        assert!(source.contains("    #[serde(rename = \"Self\")]\n    Self_,\n"));
+    }
+
+    #[test]
+    fn test_update_page_request() {
+        use crate::file::{File, Icon};
+        use crate::page::UpdatePage;
+
+        let request = UpdatePage::new()
+            .property("Done", json!({ "checkbox": true }))
+            .icon(Icon::emoji("✅"))
+            .remove_cover()
+            .request("page");
+        assert_eq!(request.get_method(), "PATCH");
+        assert_eq!(
+            request.get_body(),
+            Some(&json!({
+                "properties": { "Done": { "checkbox": true } },
+                "icon": { "type": "emoji", "emoji": "✅" },
+                "cover": null
+            }))
+        );
+
+        let page: Page = serde_json::from_value(json!({
+            "id": "page",
+            "parent": { "page_id": "parent" },
+            "properties": {},
+            "icon": { "type": "emoji", "emoji": "📄" },
+            "cover": { "type": "external", "external": { "url": "https://example.com/c.png" } },
+            "url": "https://www.notion.so/page"
+        }))
+        .unwrap();
+        assert_eq!(page.icon, Some(Icon::emoji("📄")));
+        assert_eq!(
+            page.cover,
+            Some(File::external("https://example.com/c.png"))
+        );
+    }
+
+    #[test]
+    fn test_property_values() {
+        use crate::property::{FormulaValue, PropertyValue, RollupValue};
+
+        let page: Page = serde_json::from_value(json!({
+            "id": "page",
+            "parent": { "database_id": "db" },
+            "properties": {
+                "Name": { "id": "title", "type": "title", "title": [{ "plain_text": "Write docs" }] },
+                "Due": { "id": "d", "type": "date", "date": { "start": "2024-05-01", "end": null, "time_zone": null } },
+                "Status": { "id": "s", "type": "status", "status": { "id": "1", "name": "Done", "color": "green" } },
+                "Score": { "id": "f", "type": "formula", "formula": { "type": "number", "number": 4.5 } },
+                "Total": { "id": "r", "type": "rollup", "rollup": {
+                    "type": "array", "function": "show_original",
+                    "array": [{ "type": "number", "number": 1 }]
+                } },
+                "Owner": { "id": "o", "type": "people", "people": [{ "object": "user", "id": "u1" }] },
+                "Task ID": { "id": "u", "type": "unique_id", "unique_id": { "prefix": "T", "number": 7 } },
+                "Button": { "id": "b", "type": "button", "button": {} }
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            page.property("Name").unwrap().as_text().as_deref(),
+            Some("Write docs")
+        );
+        assert_eq!(
+            page.property("Due").unwrap().as_date().unwrap().start,
+            "2024-05-01"
+        );
+        assert_eq!(
+            page.property("Status").unwrap().as_select().unwrap().name,
+            "Done"
+        );
+        assert_eq!(
+            page.property("Score").unwrap(),
+            PropertyValue::Formula(FormulaValue::Number { number: Some(4.5) })
+        );
+        assert_eq!(
+            page.property("Total").unwrap(),
+            PropertyValue::Rollup(RollupValue::Array {
+                array: vec![PropertyValue::Number(Some(1.0))]
+            })
+        );
+        assert_eq!(
+            page.property("Owner").unwrap().as_people().unwrap()[0].id,
+            "u1"
+        );
+        assert_eq!(page.property("Task ID").unwrap().as_number(), Some(7.0));
+        assert!(page.property("Missing").is_err());
+
+        let button = page.property("Button").unwrap();
+        assert_eq!(button.type_name(), "button");
+        assert_eq!(
+            serde_json::to_value(&button).unwrap(),
+            json!({ "type": "button", "button": {} })
+        );
+        assert_eq!(page.property_values().unwrap().len(), 8);
+    }
+
+    #[test]
+    fn test_property_from_items() {
+        use crate::property::{property_from_items, PropertyValue, RollupValue};
+
+        let title = property_from_items(
+            &json!({ "id": "title", "type": "title", "title": {}, "next_url": null }),
+            vec![
+                json!({ "object": "property_item", "type": "title", "title": { "plain_text": "Hello, " } }),
+                json!({ "object": "property_item", "type": "title", "title": { "plain_text": "world" } }),
+            ],
+        )
+        .unwrap();
+        assert_eq!(title.as_text().as_deref(), Some("Hello, world"));
+
+        let relation = property_from_items(
+            &json!({ "id": "r", "type": "relation", "relation": {} }),
+            vec![json!({ "type": "relation", "relation": { "id": "p1" } })],
+        )
+        .unwrap();
+        assert_eq!(relation.as_relation(), Some(vec!["p1"]));
+
+        let rollup = property_from_items(
+            &json!({ "id": "x", "type": "rollup", "rollup": { "type": "number", "number": 3, "function": "count" } }),
+            vec![json!({ "type": "relation", "relation": { "id": "p1" } })],
+        )
+        .unwrap();
+        assert_eq!(rollup.as_number(), Some(3.0));
+        assert!(matches!(rollup, PropertyValue::Rollup(_)));
+
+        // The items of array rollups are the results, not the empty array
+        // of the property item
+        let rollup = property_from_items(
+            &json!({ "id": "x", "type": "rollup", "rollup": { "type": "array", "array": [], "function": "show_original" } }),
+            vec![
+                json!({ "object": "property_item", "type": "relation", "relation": { "id": "p1" } }),
+                json!({ "object": "property_item", "type": "number", "number": 2 }),
+            ],
+        )
+        .unwrap();
+        let PropertyValue::Rollup(RollupValue::Array { array }) = rollup else {
+            panic!("expected an array rollup");
+        };
+        assert_eq!(array.len(), 2);
+        assert_eq!(array[0].as_relation(), Some(vec!["p1"]));
+        assert_eq!(array[1].as_number(), Some(2.0));
+    }
+
+    #[test]
+    fn test_create_page_request() {
+        use crate::file::Icon;
+        use crate::page::{CreatePage, Parent};
+
+        let paragraph = json!({ "object": "block", "type": "divider", "divider": {} });
+        let page = CreatePage::new(Parent::database("db"))
+            .title("Launch")
+            .property("Points", json!({ "number": 3 }))
+            .icon(Icon::emoji("🚀"))
+            .children(vec![paragraph.clone(); 120]);
+        assert_eq!(page.remaining_children().len(), 20);
+        let body = page.request().get_body().unwrap().clone();
+        assert!(body["properties"]["title"]["title"].is_array());
+
+        let page = page.title_property("Task");
+        let body = page.request().get_body().unwrap().clone();
+        assert_eq!(
+            body["parent"],
+            json!({ "type": "database_id", "database_id": "db" })
+        );
+        assert_eq!(
+            body["properties"]["Task"]["title"][0]["text"]["content"],
+            "Launch"
+        );
+        assert_eq!(body["properties"]["Points"], json!({ "number": 3 }));
+        assert_eq!(body["icon"], json!({ "type": "emoji", "emoji": "🚀" }));
+        assert_eq!(body["children"].as_array().unwrap().len(), 100);
+
+        let body = CreatePage::new(Parent::page("parent"))
+            .title("Notes")
+            .request();
+        let body = body.get_body().unwrap();
+        assert!(body["properties"]["title"].is_object());
+        assert!(body.get("children").is_none());
+    }
+
+    #[test]
+    fn test_move_request_and_properties() {
+        use crate::database::Database;
+        use crate::page::{Page, Parent};
+        use crate::page_move::moved_properties;
+
+        let request = Page::move_request("p1", &Parent::page("p2"));
+        assert_eq!(request.get_method(), "POST");
+        assert_eq!(
+            request.build_url("https://api.notion.com/v1"),
+            "https://api.notion.com/v1/pages/p1/move"
+        );
+        assert_eq!(
+            request.get_body().unwrap(),
+            &json!({ "parent": { "type": "page_id", "page_id": "p2" } })
+        );
+
+        let page: Page = serde_json::from_value(json!({
+            "id": "p1",
+            "parent": { "type": "database_id", "database_id": "db1" },
+            "properties": {
+                "Name": { "id": "title", "type": "title", "title": [{ "type": "text", "text": { "content": "Plan" }, "plain_text": "Plan" }] },
+                "Points": { "id": "a", "type": "number", "number": 3 },
+                "Notes": { "id": "b", "type": "rich_text", "rich_text": [] },
+                "Created": { "id": "c", "type": "created_time", "created_time": "2024-01-01T00:00:00.000Z" }
+            }
+        }))
+        .unwrap();
+
+        let properties = moved_properties(&page, None);
+        assert_eq!(properties.keys().collect::<Vec<_>>(), vec!["title"]);
+        assert_eq!(properties["title"]["title"][0]["text"]["content"], "Plan");
+
+        let database: Database = serde_json::from_value(json!({
+            "id": "db2",
+            "title": [],
+            "url": "",
+            "created_time": "",
+            "last_edited_time": "",
+            "properties": {
+                "Task": { "id": "title", "name": "Task", "type": "title", "title": {} },
+                "Points": { "id": "x", "name": "Points", "type": "number", "number": { "format": "number" } },
+                "Notes": { "id": "y", "name": "Notes", "type": "number", "number": { "format": "number" } },
+                "Created": { "id": "z", "name": "Created", "type": "created_time", "created_time": {} }
+            }
+        }))
+        .unwrap();
+        let properties = moved_properties(&page, Some(&database));
+        assert_eq!(
+            properties.keys().collect::<Vec<_>>(),
+            vec!["Points", "Task"]
+        );
+        assert_eq!(properties["Points"], json!({ "number": 3 }));
+    }
+
+    #[tokio::test]
+    async fn test_copy_values_for_another_workspace() {
+        use crate::block::Block;
+        use crate::copy::{CopyPageOptions, CopyValues, PageCopy};
+
+        let source = NotionClient::new("source");
+        let target = NotionClient::new("target");
+        let options = CopyPageOptions {
+            users: std::collections::HashMap::from([("u1".to_string(), "t1".to_string())]),
+            ..Default::default()
+        };
+        let copy = PageCopy::new(&source, &target, &options);
+
+        let people = json!({ "type": "people", "people": [{ "id": "u1" }, { "id": "u2" }] });
+        assert_eq!(
+            copy.property("page", "People", &people).await.unwrap(),
+            Some(json!({ "people": [{ "id": "t1" }] }))
+        );
+        let relation = json!({ "type": "relation", "relation": [{ "id": "p1" }] });
+        assert_eq!(
+            copy.property("page", "Related", &relation).await.unwrap(),
+            Some(json!({ "relation": [] }))
+        );
+        assert_eq!(copy.related_database("db1"), None);
+
+        let block: Block = serde_json::from_value(json!({
+            "id": "b1",
+            "type": "paragraph",
+            "paragraph": { "rich_text": [
+                { "type": "mention", "mention": { "type": "user", "user": { "id": "u1" } }, "plain_text": "@Ann", "annotations": {} },
+                { "type": "mention", "mention": { "type": "page", "page": { "id": "ab-cd" } }, "plain_text": "Plan", "annotations": { "bold": true } }
+            ] }
+        }))
+        .unwrap();
+        let value = copy.block(&block).await.unwrap().unwrap();
+        let text = &value["paragraph"]["rich_text"];
+        assert_eq!(text[0]["mention"]["user"]["id"], "t1");
+        assert_eq!(text[1]["type"], "text");
+        assert_eq!(text[1]["text"]["content"], "Plan");
+        assert_eq!(text[1]["text"]["link"]["url"], "https://www.notion.so/abcd");
+        assert_eq!(text[1]["annotations"]["bold"], true);
+
+        let link: Block = serde_json::from_value(json!({
+            "id": "b2",
+            "type": "link_to_page",
+            "link_to_page": { "type": "page_id", "page_id": "ab-cd" }
+        }))
+        .unwrap();
+        let value = copy.block(&link).await.unwrap().unwrap();
+        assert_eq!(value["type"], "paragraph");
+
+        // References to pages copied later are updated in a second pass
+        copy.record([
+            ("page".to_string(), "copy-page".to_string()),
+            ("p1".to_string(), "copy-p1".to_string()),
+            ("ab-cd".to_string(), "copy-cd".to_string()),
+            ("b1".to_string(), "copy-b1".to_string()),
+            ("b2".to_string(), "copy-b2".to_string()),
+        ]);
+        let updates: Vec<(String, serde_json::Value)> = copy
+            .reference_updates()
+            .into_iter()
+            .map(|request| {
+                (
+                    request.build_url("https://api.notion.com/v1"),
+                    request.get_body().unwrap().clone(),
+                )
+            })
+            .collect();
+        assert_eq!(updates.len(), 3);
+        assert_eq!(updates[0].0, "https://api.notion.com/v1/blocks/copy-b1");
+        let text = &updates[0].1["paragraph"]["rich_text"];
+        assert_eq!(text[0]["mention"]["user"]["id"], "t1");
+        assert_eq!(text[1]["mention"]["page"]["id"], "copy-cd");
+        assert_eq!(
+            updates[1].1,
+            json!({ "paragraph": { "rich_text": [{
+                "type": "mention", "mention": { "type": "page", "page": { "id": "copy-cd" } }
+            }] } })
+        );
+        assert_eq!(updates[2].0, "https://api.notion.com/v1/pages/copy-page");
+        assert_eq!(
+            updates[2].1,
+            json!({ "properties": { "Related": { "relation": [{ "id": "copy-p1" }] } } })
+        );
+        assert!(copy.reference_updates().is_empty());
+
+        let options = CopyPageOptions {
+            same_workspace: true,
+            ..Default::default()
+        };
+        let copy = PageCopy::new(&source, &target, &options);
+        assert_eq!(
+            copy.property("page", "Related", &relation).await.unwrap(),
+            Some(json!({ "relation": [{ "id": "p1" }] }))
+        );
+        assert_eq!(
+            copy.block(&link).await.unwrap().unwrap()["type"],
+            "link_to_page"
+        );
+    }
+
+    #[test]
+    fn test_render_template() {
+        use crate::block::BlockTree;
+        use crate::page::Page;
+        use crate::template::{render_text, PageTemplate};
+
+        let block = |kind: &str, text: &str, children: Vec<BlockTree>| BlockTree {
+            value: json!({
+                "object": "block",
+                "type": kind,
+                kind: { "rich_text": [{ "type": "text", "text": { "content": text }, "plain_text": text }] }
+            }),
+            children,
+        };
+        let text = |block: &BlockTree| {
+            let kind = block.value["type"].as_str().unwrap().to_string();
+            block.value[&kind]["rich_text"][0]["text"]["content"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+        let page: Page = serde_json::from_value(json!({
+            "id": "t1",
+            "parent": { "type": "page_id", "page_id": "p" },
+            "properties": {
+                "title": { "id": "title", "type": "title", "title": [{ "type": "text", "text": { "content": "Weekly {{week}}" }, "plain_text": "Weekly {{week}}" }] }
+            }
+        }))
+        .unwrap();
+        let template = PageTemplate {
+            page,
+            blocks: vec![
+                block("heading_2", "Attendees", vec![]),
+                block("paragraph", "{{#attendees}}", vec![]),
+                block("bulleted_list_item", "{{.}} ({{team}})", vec![]),
+                block("paragraph", "{{/attendees}}", vec![]),
+                block("paragraph", "{{# topics }}", vec![]),
+                block(
+                    "toggle",
+                    "{{title}}",
+                    vec![block("paragraph", "Owner: {{owner.name}}", vec![])],
+                ),
+                block("paragraph", "{{/topics}}", vec![]),
+                block("paragraph", "{{#archived}}", vec![]),
+                block("paragraph", "Archived", vec![]),
+                block("paragraph", "{{/archived}}", vec![]),
+            ],
+        };
+        let variables = json!({
+            "week": 42,
+            "team": "Core",
+            "archived": false,
+            "attendees": ["Ann", "Bo"],
+            "topics": [
+                { "title": "Release", "owner": { "name": "Ann" } },
+                { "title": "Hiring", "owner": { "name": "Bo" } }
+            ]
+        });
+        let rendered = template.render(&variables).unwrap();
+        assert_eq!(
+            rendered.page.properties["title"]["title"][0]["text"]["content"],
+            "Weekly 42"
+        );
+        let texts: Vec<String> = rendered.blocks.iter().map(text).collect();
+        assert_eq!(
+            texts,
+            vec!["Attendees", "Ann (Core)", "Bo (Core)", "Release", "Hiring"]
+        );
+        assert_eq!(text(&rendered.blocks[4].children[0]), "Owner: Bo");
+
+        assert_eq!(
+            render_text("{{ missing }}", &variables)
+                .unwrap_err()
+                .to_string(),
+            "Invalid request: Template: no value for \"missing\""
+        );
+        let unclosed = PageTemplate {
+            page: template.page.clone(),
+            blocks: vec![block("paragraph", "{{#topics}}", vec![])],
+        };
+        assert!(unclosed.render(&variables).is_err());
+
+        let run = |text: &str, bold: bool| {
+            json!({
+                "type": "text",
+                "text": { "content": text },
+                "annotations": { "bold": bold },
+                "plain_text": text
+            })
+        };
+        let verbatim = |kind: &str, content: serde_json::Value| BlockTree {
+            value: json!({ "object": "block", "type": kind, kind: content }),
+            children: vec![],
+        };
+        let formatted = PageTemplate {
+            page: template.page.clone(),
+            blocks: vec![
+                verbatim(
+                    "paragraph",
+                    json!({ "rich_text": [run("Week {{we", false), run("ek}} notes", true)] }),
+                ),
+                verbatim(
+                    "paragraph",
+                    json!({ "rich_text": [run("{{", false), run("team", true), run("}}", false)] }),
+                ),
+                verbatim(
+                    "code",
+                    json!({ "rich_text": [run("{{#each items}}", false)], "language": "handlebars" }),
+                ),
+                verbatim("equation", json!({ "expression": "\\frac{{a}}{b}" })),
+            ],
+        };
+        let rendered = formatted.render(&variables).unwrap();
+        let rich_text = &rendered.blocks[0].value["paragraph"]["rich_text"];
+        assert_eq!(rich_text[0]["text"]["content"], "Week 42");
+        assert_eq!(rich_text[1]["text"]["content"], " notes");
+        assert_eq!(rich_text[1]["annotations"]["bold"], true);
+        assert_eq!(
+            rendered.blocks[1].value["paragraph"]["rich_text"],
+            json!([run("Core", false)])
+        );
+        assert_eq!(rendered.blocks[2], formatted.blocks[2]);
+        assert_eq!(rendered.blocks[3], formatted.blocks[3]);
+    }
 }
@@ -0,0 +1,424 @@
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use crate::database::{same_id, Database};
+use crate::error::NotionError;
+use crate::schema::{
+    merge_select_options, PropertySchema, RelationKind, SelectConfig, SelectOption,
+};
+
+/// Desired state of a single database property
+#[derive(Debug, Clone, PartialEq)]
+pub struct DesiredProperty {
+    pub schema: PropertySchema,
+    /// Previous names of the property, checked when no property has the new name
+    pub renamed_from: Vec<String>,
+}
+
+/// Declarative description of the schema a database should have
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DesiredSchema {
+    pub properties: BTreeMap<String, DesiredProperty>,
+}
+
+impl DesiredSchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares a property that should exist with the given schema
+    pub fn property(mut self, name: &str, schema: PropertySchema) -> Self {
+        self.properties.insert(
+            name.to_string(),
+            DesiredProperty {
+                schema,
+                renamed_from: Vec::new(),
+            },
+        );
+        self
+    }
+
+    /// Declares a property that may currently exist under an older name
+    pub fn renamed_property(mut self, name: &str, old_name: &str, schema: PropertySchema) -> Self {
+        let property = self
+            .properties
+            .entry(name.to_string())
+            .or_insert_with(|| DesiredProperty {
+                schema: schema.clone(),
+                renamed_from: Vec::new(),
+            });
+        property.schema = schema;
+        property.renamed_from.push(old_name.to_string());
+        self
+    }
+}
+
+/// A single `update_database` operation produced by a migration
+#[derive(Debug, Clone, PartialEq)]
+pub enum MigrationStep {
+    RemoveProperty {
+        id: String,
+        name: String,
+    },
+    RenameProperty {
+        id: String,
+        from: String,
+        to: String,
+    },
+    /// Changes the type of a property; existing values may be lost
+    ChangeType {
+        id: String,
+        name: String,
+        from: String,
+        schema: PropertySchema,
+    },
+    /// Changes the configuration of a property while keeping its type
+    UpdateConfig {
+        id: String,
+        name: String,
+        schema: PropertySchema,
+    },
+    AddProperty {
+        name: String,
+        schema: PropertySchema,
+    },
+    AddSelectOptions {
+        id: String,
+        name: String,
+        /// The current schema, whose options are kept
+        current: PropertySchema,
+        options: Vec<SelectOption>,
+    },
+}
+
+impl MigrationStep {
+    /// Whether the step can lose data stored in the database
+    pub fn is_destructive(&self) -> bool {
+        matches!(
+            self,
+            MigrationStep::RemoveProperty { .. } | MigrationStep::ChangeType { .. }
+        )
+    }
+
+    /// The `properties` object to send with `update_database` for this step
+    pub fn properties_patch(&self) -> Value {
+        let (key, value) = match self {
+            MigrationStep::RemoveProperty { id, .. } => (id.clone(), Value::Null),
+            MigrationStep::RenameProperty { id, to, .. } => {
+                (id.clone(), serde_json::json!({ "name": to }))
+            }
+            MigrationStep::ChangeType { id, schema, .. }
+            | MigrationStep::UpdateConfig { id, schema, .. } => {
+                (id.clone(), schema.to_request_value())
+            }
+            MigrationStep::AddProperty { name, schema } => {
+                (name.clone(), schema.to_request_value())
+            }
+            MigrationStep::AddSelectOptions {
+                id,
+                current,
+                options,
+                ..
+            } => {
+                // The current options are kept by listing them with the new ones
+                let current_options = select_options(current);
+                let mut all = current_options.to_vec();
+                all.extend(options.iter().cloned());
+                let merged = merge_select_options(current_options, &all);
+                let config = serde_json::json!({ current.type_name(): { "options": merged } });
+                (id.clone(), config)
+            }
+        };
+        let mut patch = Map::new();
+        patch.insert(key, value);
+        Value::Object(patch)
+    }
+}
+
+impl fmt::Display for MigrationStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationStep::RemoveProperty { name, .. } => {
+                write!(f, "remove property \"{}\"", name)
+            }
+            MigrationStep::RenameProperty { from, to, .. } => {
+                write!(f, "rename property \"{}\" to \"{}\"", from, to)
+            }
+            MigrationStep::ChangeType {
+                name, from, schema, ..
+            } => write!(
+                f,
+                "change type of \"{}\" from {} to {}",
+                name,
+                from,
+                schema.type_name()
+            ),
+            MigrationStep::UpdateConfig { name, schema, .. } => {
+                write!(
+                    f,
+                    "update {} configuration of \"{}\"",
+                    schema.type_name(),
+                    name
+                )
+            }
+            MigrationStep::AddProperty { name, schema } => {
+                write!(f, "add property \"{}\" ({})", name, schema.type_name())
+            }
+            MigrationStep::AddSelectOptions { name, options, .. } => {
+                let names: Vec<&str> = options.iter().map(|o| o.name.as_str()).collect();
+                write!(f, "add options to \"{}\": {}", name, names.join(", "))
+            }
+        }?;
+        if self.is_destructive() {
+            write!(f, " [destructive]")?;
+        }
+        Ok(())
+    }
+}
+
+/// Options controlling how a migration is applied
+#[derive(Debug, Clone, Default)]
+pub struct MigrationOptions {
+    /// Allow removing properties and changing property types
+    pub allow_destructive: bool,
+    /// Only compute the plan without updating the database
+    pub dry_run: bool,
+}
+
+/// Ordered list of steps turning the current schema into the desired one
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MigrationPlan {
+    pub steps: Vec<MigrationStep>,
+}
+
+impl MigrationPlan {
+    /// Compares the schema of `current` against `desired`.
+    ///
+    /// Steps are ordered so that each one is valid when applied after the
+    /// previous ones: removals, renames, type and configuration changes,
+    /// additions and finally new select options. Existing select options
+    /// that are not listed in the desired schema are kept, and configuration
+    /// fields the desired schema does not declare are not compared.
+    pub fn diff(current: &Database, desired: &DesiredSchema) -> Self {
+        let mut removals = Vec::new();
+        let mut renames = Vec::new();
+        let mut changes = Vec::new();
+        let mut additions = Vec::new();
+        let mut options = Vec::new();
+        let mut matched = HashSet::new();
+
+        let current_title = current
+            .properties
+            .values()
+            .find(|property| property.schema == PropertySchema::Title);
+
+        for (name, wanted) in &desired.properties {
+            let existing = current.properties.get(name).or_else(|| {
+                wanted
+                    .renamed_from
+                    .iter()
+                    .find_map(|old| current.properties.get(old))
+                    .or(if wanted.schema == PropertySchema::Title {
+                        // A database has exactly one title property
+                        current_title
+                    } else {
+                        None
+                    })
+            });
+
+            let Some(existing) = existing.filter(|p| !matched.contains(&p.name)) else {
+                additions.push(MigrationStep::AddProperty {
+                    name: name.clone(),
+                    schema: wanted.schema.clone(),
+                });
+                continue;
+            };
+            matched.insert(existing.name.clone());
+            let id = existing.id.clone().unwrap_or_else(|| existing.name.clone());
+
+            if &existing.name != name {
+                renames.push(MigrationStep::RenameProperty {
+                    id: id.clone(),
+                    from: existing.name.clone(),
+                    to: name.clone(),
+                });
+            }
+
+            if existing.schema.type_name() != wanted.schema.type_name() {
+                changes.push(MigrationStep::ChangeType {
+                    id,
+                    name: name.clone(),
+                    from: existing.schema.type_name().to_string(),
+                    schema: wanted.schema.clone(),
+                });
+                continue;
+            }
+
+            match (&existing.schema, &wanted.schema) {
+                (PropertySchema::Select(have), PropertySchema::Select(want))
+                | (PropertySchema::MultiSelect(have), PropertySchema::MultiSelect(want)) => {
+                    let missing = missing_options(have, want);
+                    if !missing.is_empty() {
+                        options.push(MigrationStep::AddSelectOptions {
+                            id,
+                            name: name.clone(),
+                            current: existing.schema.clone(),
+                            options: missing,
+                        });
+                    }
+                }
+                // Status options cannot be changed through the API
+                (PropertySchema::Status(_), PropertySchema::Status(_)) => {}
+                (have, want) if config_changed(have, want) => {
+                    changes.push(MigrationStep::UpdateConfig {
+                        id,
+                        name: name.clone(),
+                        schema: want.clone(),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        for property in current.properties.values() {
+            if matched.contains(&property.name) || property.schema == PropertySchema::Title {
+                continue;
+            }
+            removals.push(MigrationStep::RemoveProperty {
+                id: property.id.clone().unwrap_or_else(|| property.name.clone()),
+                name: property.name.clone(),
+            });
+        }
+
+        let mut steps = removals;
+        steps.extend(renames);
+        steps.extend(changes);
+        steps.extend(additions);
+        steps.extend(options);
+        Self { steps }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Steps that can lose data
+    pub fn destructive_steps(&self) -> impl Iterator<Item = &MigrationStep> {
+        self.steps.iter().filter(|step| step.is_destructive())
+    }
+
+    /// Fails if the plan contains destructive steps that are not allowed
+    pub fn check(&self, options: &MigrationOptions) -> Result<(), NotionError> {
+        if options.allow_destructive {
+            return Ok(());
+        }
+        let destructive: Vec<String> = self.destructive_steps().map(|s| s.to_string()).collect();
+        if destructive.is_empty() {
+            Ok(())
+        } else {
+            Err(NotionError::InvalidRequest(format!(
+                "migration contains destructive steps: {}",
+                destructive.join("; ")
+            )))
+        }
+    }
+
+    /// Human-readable description of the plan, one step per line
+    pub fn report(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for MigrationPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.steps.is_empty() {
+            return writeln!(f, "schema is up to date");
+        }
+        for (index, step) in self.steps.iter().enumerate() {
+            writeln!(f, "{}. {}", index + 1, step)?;
+        }
+        Ok(())
+    }
+}
+
+fn select_options(schema: &PropertySchema) -> &[SelectOption] {
+    match schema {
+        PropertySchema::Select(config) | PropertySchema::MultiSelect(config) => &config.options,
+        _ => &[],
+    }
+}
+
+/// Whether every field of `want` is in `have` with the same value, so that
+/// fields the API adds to a configuration are not compared
+fn declares_same(have: &Value, want: &Value) -> bool {
+    match (have, want) {
+        (Value::Object(have), Value::Object(want)) => want
+            .iter()
+            .all(|(key, want)| have.get(key).is_some_and(|have| declares_same(have, want))),
+        (have, want) => have == want,
+    }
+}
+
+/// A formula expression without the whitespace outside of string literals,
+/// which Notion reformats
+fn normalized_expression(expression: &str) -> String {
+    let mut normalized = String::with_capacity(expression.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in expression.chars() {
+        if in_string {
+            in_string = escaped || c != '"';
+            escaped = !escaped && c == '\\';
+        } else if c == '"' {
+            in_string = true;
+        } else if c.is_whitespace() {
+            continue;
+        }
+        normalized.push(c);
+    }
+    normalized
+}
+
+/// Whether the configuration declared by `want` differs from the one of a
+/// property of the same type. Fields that the API fills in but a desired
+/// schema leaves out, such as the synced property of a relation or the IDs
+/// of a rollup, only count when they are declared, and formulas are
+/// compared regardless of how Notion formats them.
+fn config_changed(have: &PropertySchema, want: &PropertySchema) -> bool {
+    let differs = |have: &Option<String>, want: &Option<String>| want.is_some() && have != want;
+    match (have, want) {
+        (PropertySchema::Relation(have), PropertySchema::Relation(want)) => {
+            !same_id(&have.database_id, &want.database_id)
+                || match (&have.kind, &want.kind) {
+                    (RelationKind::Single, RelationKind::Single) => false,
+                    (RelationKind::Dual(have), RelationKind::Dual(want)) => {
+                        differs(&have.synced_property_name, &want.synced_property_name)
+                    }
+                    _ => true,
+                }
+        }
+        (PropertySchema::Rollup(have), PropertySchema::Rollup(want)) => {
+            have.function != want.function
+                || differs(&have.relation_property_name, &want.relation_property_name)
+                || differs(&have.relation_property_id, &want.relation_property_id)
+                || differs(&have.rollup_property_name, &want.rollup_property_name)
+                || differs(&have.rollup_property_id, &want.rollup_property_id)
+        }
+        (PropertySchema::UniqueId(have), PropertySchema::UniqueId(want)) => {
+            differs(&have.prefix, &want.prefix)
+        }
+        (PropertySchema::Formula(have), PropertySchema::Formula(want)) => {
+            normalized_expression(&have.expression) != normalized_expression(&want.expression)
+        }
+        (have, want) => !declares_same(&have.config(), &want.config()),
+    }
+}
+
+fn missing_options(have: &SelectConfig, want: &SelectConfig) -> Vec<SelectOption> {
+    want.options
+        .iter()
+        .filter(|option| !have.options.iter().any(|o| o.name == option.name))
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,346 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+use crate::database::{RichText, MAX_PAGE_SIZE};
+use crate::error::NotionError;
+use crate::file::{File, Icon};
+use crate::property::PropertyValue;
+use crate::request::RequestBuilder;
+
+/// Page object returned by the Notion API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page {
+    pub id: String,
+    pub parent: Value,
+    pub properties: Value,
+    #[serde(default)]
+    pub icon: Option<Icon>,
+    #[serde(default)]
+    pub cover: Option<File>,
+    #[serde(default)]
+    pub url: String,
+    /// URL of the page when it is published to the web
+    #[serde(default)]
+    pub public_url: Option<String>,
+    /// ISO 8601 date and time when the page was created
+    #[serde(default)]
+    pub created_time: String,
+    /// ISO 8601 date and time when the page was last edited
+    #[serde(default)]
+    pub last_edited_time: String,
+    /// Whether the page has been archived
+    #[serde(default)]
+    pub archived: bool,
+    /// Whether the page has been moved to the trash
+    #[serde(default)]
+    pub in_trash: bool,
+}
+
+impl Page {
+    /// Whether the page is archived or in the trash
+    pub fn is_archived(&self) -> bool {
+        self.archived || self.in_trash
+    }
+
+    /// Typed value of a property, by name
+    pub fn property(&self, name: &str) -> Result<PropertyValue, NotionError> {
+        let value = self
+            .properties
+            .get(name)
+            .ok_or_else(|| NotionError::InvalidProperty {
+                property: name.to_string(),
+                message: "the page has no such property".to_string(),
+            })?;
+        serde_json::from_value(value.clone()).map_err(|e| NotionError::InvalidProperty {
+            property: name.to_string(),
+            message: e.to_string(),
+        })
+    }
+
+    /// Typed values of every property, by name
+    pub fn property_values(&self) -> Result<BTreeMap<String, PropertyValue>, NotionError> {
+        self.properties
+            .as_object()
+            .into_iter()
+            .flatten()
+            .map(|(name, _)| Ok((name.clone(), self.property(name)?)))
+            .collect()
+    }
+
+    pub fn get_request(page_id: &str) -> RequestBuilder {
+        RequestBuilder::new(&format!("/pages/{}", page_id))
+    }
+
+    /// Request retrieving a page with only the given properties, by property ID
+    pub fn get_with_properties_request(page_id: &str, property_ids: &[String]) -> RequestBuilder {
+        property_ids
+            .iter()
+            .fold(Self::get_request(page_id), |request, property_id| {
+                request.append_query_param("filter_properties", property_id)
+            })
+    }
+
+    /// Request retrieving one page of the items of a property, following
+    /// `cursor` when given. Relation, people, rich text and title values are
+    /// only complete through this endpoint.
+    pub fn property_request(
+        page_id: &str,
+        property_id: &str,
+        cursor: Option<&str>,
+    ) -> RequestBuilder {
+        let request =
+            RequestBuilder::new(&format!("/pages/{}/properties/{}", page_id, property_id))
+                .query_param("page_size", MAX_PAGE_SIZE);
+        match cursor {
+            Some(cursor) => request.query_param("start_cursor", cursor),
+            None => request,
+        }
+    }
+
+    /// Request creating a page in a database with the given property values
+    pub fn create_in_database_request(database_id: &str, properties: Value) -> RequestBuilder {
+        CreatePage::new(Parent::database(database_id))
+            .properties(properties)
+            .request()
+    }
+
+    /// Request moving a page under another page or into a database
+    pub fn move_request(page_id: &str, parent: &Parent) -> RequestBuilder {
+        RequestBuilder::new(&format!("/pages/{}/move", page_id))
+            .method("POST")
+            .json_body(serde_json::json!({ "parent": parent }))
+    }
+
+    /// Request updating property values of a page. Properties that are not
+    /// included keep their values.
+    pub fn update_properties_request(page_id: &str, properties: Value) -> RequestBuilder {
+        UpdatePage::new().properties(properties).request(page_id)
+    }
+
+    /// Request moving a page to the trash, or restoring it when `archived` is false
+    pub fn archive_request(page_id: &str, archived: bool) -> RequestBuilder {
+        UpdatePage::new().archived(archived).request(page_id)
+    }
+}
+
+/// Where a page or block lives
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Parent {
+    #[serde(rename = "page_id")]
+    Page { page_id: String },
+    #[serde(rename = "database_id")]
+    Database { database_id: String },
+    /// A data source of a database. Only accepted by clients set to
+    /// `DATA_SOURCES_API_VERSION` or later with `NotionClient::with_api_version`.
+    #[serde(rename = "data_source_id")]
+    DataSource { data_source_id: String },
+    #[serde(rename = "block_id")]
+    Block { block_id: String },
+    #[serde(rename = "workspace")]
+    Workspace { workspace: bool },
+}
+
+impl Parent {
+    pub fn page(page_id: &str) -> Self {
+        Parent::Page {
+            page_id: page_id.to_string(),
+        }
+    }
+
+    pub fn database(database_id: &str) -> Self {
+        Parent::Database {
+            database_id: database_id.to_string(),
+        }
+    }
+
+    /// A data source of a database, see `Parent::DataSource`
+    pub fn data_source(data_source_id: &str) -> Self {
+        Parent::DataSource {
+            data_source_id: data_source_id.to_string(),
+        }
+    }
+}
+
+/// Maximum number of blocks that can be sent in one request
+pub const MAX_CHILDREN: usize = 100;
+
+/// Builder for a `POST /pages` request
+#[derive(Debug, Clone)]
+pub struct CreatePage {
+    parent: Parent,
+    title: Option<Vec<RichText>>,
+    title_property: Option<String>,
+    properties: Map<String, Value>,
+    icon: Option<Icon>,
+    cover: Option<File>,
+    children: Vec<Value>,
+}
+
+impl CreatePage {
+    pub fn new(parent: Parent) -> Self {
+        Self {
+            parent,
+            title: None,
+            title_property: None,
+            properties: Map::new(),
+            icon: None,
+            cover: None,
+            children: Vec::new(),
+        }
+    }
+
+    /// Sets a plain text title
+    pub fn title(self, title: &str) -> Self {
+        self.title_rich_text(vec![RichText::text(title)])
+    }
+
+    /// Sets the title. In databases it is stored in the property named by
+    /// `title_property`, or else under `title`, the ID of the title property of
+    /// every database.
+    pub fn title_rich_text(mut self, title: Vec<RichText>) -> Self {
+        self.title = Some(title);
+        self
+    }
+
+    /// Name of the title property of the parent database
+    pub fn title_property(mut self, name: &str) -> Self {
+        self.title_property = Some(name.to_string());
+        self
+    }
+
+    /// Sets the value of a property, keyed by property name or ID
+    pub fn property(mut self, name: &str, value: Value) -> Self {
+        self.properties.insert(name.to_string(), value);
+        self
+    }
+
+    /// Sets the values of several properties, keyed by property name or ID
+    pub fn properties(mut self, properties: Value) -> Self {
+        if let Value::Object(properties) = properties {
+            self.properties.extend(properties);
+        }
+        self
+    }
+
+    pub fn icon(mut self, icon: Icon) -> Self {
+        self.icon = Some(icon);
+        self
+    }
+
+    pub fn cover(mut self, cover: File) -> Self {
+        self.cover = Some(cover);
+        self
+    }
+
+    /// Adds blocks to the content of the page, in the shape returned by
+    /// `Block::to_request_value`
+    pub fn children(mut self, children: Vec<Value>) -> Self {
+        self.children.extend(children);
+        self
+    }
+
+    pub fn parent(&self) -> &Parent {
+        &self.parent
+    }
+
+    /// Blocks beyond the first `MAX_CHILDREN`, which have to be appended
+    /// after the page is created
+    pub fn remaining_children(&self) -> &[Value] {
+        self.children.get(MAX_CHILDREN..).unwrap_or_default()
+    }
+
+    /// The request, with at most `MAX_CHILDREN` blocks. The title is keyed by
+    /// `title_property`, or by `title`, which is the ID of the title property
+    /// of every database and the name of the title of other pages.
+    pub fn request(&self) -> RequestBuilder {
+        let mut properties = self.properties.clone();
+        if let Some(title) = &self.title {
+            let key = self.title_property.as_deref().unwrap_or("title");
+            properties.insert(key.to_string(), serde_json::json!({ "title": title }));
+        }
+        let mut body = serde_json::json!({
+            "parent": self.parent,
+            "properties": properties,
+        });
+        if let Some(icon) = &self.icon {
+            body["icon"] = serde_json::json!(icon);
+        }
+        if let Some(cover) = &self.cover {
+            body["cover"] = serde_json::json!(cover);
+        }
+        if !self.children.is_empty() {
+            let end = self.children.len().min(MAX_CHILDREN);
+            body["children"] = Value::from(&self.children[..end]);
+        }
+        RequestBuilder::new("/pages").method("POST").json_body(body)
+    }
+}
+
+/// Builder for a `PATCH /pages/{id}` request; only the fields that are set are sent
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UpdatePage {
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    properties: Map<String, Value>,
+    /// `Some(None)` removes the icon
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon: Option<Option<Icon>>,
+    /// `Some(None)` removes the cover
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cover: Option<Option<File>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    archived: Option<bool>,
+}
+
+impl UpdatePage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the value of a property, keyed by property name or ID
+    pub fn property(mut self, name: &str, value: Value) -> Self {
+        self.properties.insert(name.to_string(), value);
+        self
+    }
+
+    /// Sets the values of several properties, keyed by property name or ID
+    pub fn properties(mut self, properties: Value) -> Self {
+        if let Value::Object(properties) = properties {
+            self.properties.extend(properties);
+        }
+        self
+    }
+
+    pub fn icon(mut self, icon: Icon) -> Self {
+        self.icon = Some(Some(icon));
+        self
+    }
+
+    pub fn remove_icon(mut self) -> Self {
+        self.icon = Some(None);
+        self
+    }
+
+    pub fn cover(mut self, cover: File) -> Self {
+        self.cover = Some(Some(cover));
+        self
+    }
+
+    pub fn remove_cover(mut self) -> Self {
+        self.cover = Some(None);
+        self
+    }
+
+    /// Moves the page to the trash, or restores it when false
+    pub fn archived(mut self, archived: bool) -> Self {
+        self.archived = Some(archived);
+        self
+    }
+
+    pub fn request(&self, page_id: &str) -> RequestBuilder {
+        RequestBuilder::new(&format!("/pages/{}", page_id))
+            .method("PATCH")
+            .json_body(serde_json::to_value(self).unwrap())
+    }
+}
@@ -0,0 +1,154 @@
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+use crate::client::NotionClient;
+use crate::copy::{CopyPageOptions, PageCopy};
+use crate::database::Database;
+use crate::duplicate::writable_property_value;
+use crate::error::NotionError;
+use crate::page::{Page, Parent};
+use crate::response::ObjectResponse;
+
+/// Options for `NotionClient::move_page`
+#[derive(Debug, Clone, Default)]
+pub struct MoveOptions {
+    /// When the move endpoint does not exist, copy the page to the new
+    /// parent and move the original to the trash. The copy gets new IDs and
+    /// loses the comments, history and backlinks of the original, so this is
+    /// off by default.
+    pub allow_copy_fallback: bool,
+}
+
+/// How `NotionClient::move_page` moved a page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveStrategy {
+    /// The page was moved by Notion and kept its ID, content and history
+    Moved,
+    /// The move endpoint does not exist, so a copy was created under the new
+    /// parent and the original was moved to the trash
+    CopiedAndArchived,
+}
+
+/// Result of moving a page
+#[derive(Debug, Clone)]
+pub struct MoveReport {
+    pub strategy: MoveStrategy,
+    /// The page at its new location. After `CopiedAndArchived` this is a new
+    /// page with a new ID.
+    pub page: Page,
+    /// Error of the move endpoint that caused the fallback
+    pub fallback_reason: Option<String>,
+    /// IDs of the original page, its blocks and its child pages and
+    /// databases, mapped to the IDs of their copies. Empty when moved.
+    pub ids: HashMap<String, String>,
+}
+
+/// Whether an error of the move endpoint means that the endpoint does not
+/// exist, as opposed to this move being rejected
+fn move_endpoint_missing(error: &NotionError) -> bool {
+    matches!(error, NotionError::ApiError { code, .. } if code == "invalid_request_url")
+}
+
+/// Name under which a property of a page is set on its copy under another
+/// parent. The title is always kept; other properties only when the new
+/// parent is a database with a property of the same name and type.
+pub(crate) fn moved_property_name(
+    name: &str,
+    kind: &str,
+    target: Option<&Database>,
+) -> Option<String> {
+    match target {
+        None => (kind == "title").then(|| "title".to_string()),
+        Some(database) => database
+            .properties
+            .iter()
+            .find(|(target_name, property)| {
+                property.schema.type_name() == kind && (kind == "title" || *target_name == name)
+            })
+            .map(|(target_name, _)| target_name.clone()),
+    }
+}
+
+/// Property values of a page to set on its copy, keyed by
+/// `moved_property_name`
+pub(crate) fn moved_properties(page: &Page, target: Option<&Database>) -> Map<String, Value> {
+    let mut properties = Map::new();
+    for (name, value) in page.properties.as_object().into_iter().flatten() {
+        let kind = value["type"].as_str().unwrap_or_default();
+        let Some(key) = moved_property_name(name, kind, target) else {
+            continue;
+        };
+        properties.extend(writable_property_value(value).map(|value| (key, value)));
+    }
+    properties
+}
+
+impl NotionClient {
+    /// Moves a page under another page or into a database.
+    ///
+    /// Uses Notion's move endpoint, which keeps the page as it is. When the
+    /// endpoint does not exist and `MoveOptions::allow_copy_fallback` is set,
+    /// the page is copied to the new parent instead, as by `copy_page` within
+    /// the workspace: its title, the properties the new parent database has
+    /// with the same name and type, icon, cover and content are copied, with
+    /// files hosted by Notion uploaded again, and child pages and databases
+    /// are copied with it. Only once everything is copied is the original
+    /// page moved to the trash; if any step fails, the partial copy is moved
+    /// to the trash instead and the original is left as it was. Comments,
+    /// history and links from outside the page are not carried over.
+    pub async fn move_page(
+        &self,
+        page_id: &str,
+        new_parent: Parent,
+        options: MoveOptions,
+    ) -> Result<MoveReport, NotionError> {
+        let error = match self
+            .post::<ObjectResponse<Page>>(Page::move_request(page_id, &new_parent))
+            .await
+        {
+            Ok(moved) => {
+                return Ok(MoveReport {
+                    strategy: MoveStrategy::Moved,
+                    page: moved.data,
+                    fallback_reason: None,
+                    ids: HashMap::new(),
+                })
+            }
+            Err(error) if move_endpoint_missing(&error) && options.allow_copy_fallback => error,
+            Err(error) => return Err(error),
+        };
+
+        let copy_options = CopyPageOptions {
+            same_workspace: true,
+            ..Default::default()
+        };
+        let copy = PageCopy::new(self, self, &copy_options);
+        let copied = async {
+            let page = copy.copy_page(page_id, new_parent).await?;
+            for request in copy.reference_updates() {
+                self.patch::<Value>(request).await?;
+            }
+            self.patch::<ObjectResponse<Page>>(Page::archive_request(page_id, true))
+                .await?;
+            Ok(page)
+        }
+        .await;
+        match copied {
+            Ok(page) => Ok(MoveReport {
+                strategy: MoveStrategy::CopiedAndArchived,
+                page,
+                fallback_reason: Some(error.to_string()),
+                ids: copy.into_ids(),
+            }),
+            Err(error) => {
+                // Trashing the partial copy also trashes everything copied into it
+                if let Some(copy_id) = copy.copy_of(page_id) {
+                    let _ = self
+                        .patch::<ObjectResponse<Page>>(Page::archive_request(&copy_id, true))
+                        .await;
+                }
+                Err(error)
+            }
+        }
+    }
+}
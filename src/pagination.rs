@@ -0,0 +1,55 @@
+use futures::stream::{self, Stream, StreamExt, Take, TryCollect, TryStreamExt};
+use std::future::Future;
+
+use crate::error::NotionError;
+use crate::response::ListResponse;
+
+/// Turns a cursor-based list endpoint into a stream of its items.
+///
+/// `fetch` is called with `None` for the first page and with the previous
+/// page's `next_cursor` afterwards. Pages are only requested when the stream
+/// is polled past the items already fetched, and the stream ends after the
+/// first error.
+pub fn paginate<'a, T, F, Fut>(fetch: F) -> impl Stream<Item = Result<T, NotionError>> + 'a
+where
+    T: 'a,
+    F: FnMut(Option<String>) -> Fut + 'a,
+    Fut: Future<Output = Result<ListResponse<T>, NotionError>> + 'a,
+{
+    enum Cursor {
+        Start,
+        Next(String),
+        Done,
+    }
+
+    stream::unfold((fetch, Cursor::Start), |(mut fetch, cursor)| async move {
+        let cursor = match cursor {
+            Cursor::Start => None,
+            Cursor::Next(cursor) => Some(cursor),
+            Cursor::Done => return None,
+        };
+        match fetch(cursor).await {
+            Ok(page) => {
+                let next = match page.next_cursor {
+                    Some(cursor) if page.has_more => Cursor::Next(cursor),
+                    _ => Cursor::Done,
+                };
+                Some((Ok(page.results), (fetch, next)))
+            }
+            Err(error) => Some((Err(error), (fetch, Cursor::Done))),
+        }
+    })
+    .map_ok(|results| stream::iter(results.into_iter().map(Ok)))
+    .try_flatten()
+}
+
+/// Convenience methods for streams returned by the `*_stream` client methods
+pub trait PaginatedStreamExt<T>: Stream<Item = Result<T, NotionError>> + Sized {
+    /// Collects every item of the stream, stopping after `max` items if given.
+    /// No further pages are requested once the maximum has been reached.
+    fn collect_all(self, max: Option<usize>) -> TryCollect<Take<Self>, Vec<T>> {
+        self.take(max.unwrap_or(usize::MAX)).try_collect()
+    }
+}
+
+impl<T, S> PaginatedStreamExt<T> for S where S: Stream<Item = Result<T, NotionError>> + Sized {}
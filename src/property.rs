@@ -0,0 +1,419 @@
+use futures::TryStreamExt;
+use serde::de::{self, Deserializer};
+use serde::ser::{SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::client::NotionClient;
+use crate::database::{plain_text, RichText};
+use crate::error::NotionError;
+use crate::file::File;
+use crate::page::Page;
+use crate::response::{ListResponse, User};
+use crate::schema::SelectOption;
+
+/// Value of a date property; `end` is set for date ranges
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DateValue {
+    /// ISO 8601 date, or date and time
+    pub start: String,
+    #[serde(default)]
+    pub end: Option<String>,
+    #[serde(default)]
+    pub time_zone: Option<String>,
+}
+
+/// A file of a files property
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NamedFile {
+    #[serde(default)]
+    pub name: String,
+    #[serde(flatten)]
+    pub file: File,
+}
+
+/// A page referenced by a relation property
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PageReference {
+    pub id: String,
+}
+
+/// Value of a unique ID property, such as `TASK-42`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UniqueIdValue {
+    #[serde(default)]
+    pub prefix: Option<String>,
+    #[serde(default)]
+    pub number: Option<u64>,
+}
+
+/// Value of a verification property of a wiki page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerificationValue {
+    /// `verified` or `unverified`
+    pub state: String,
+    #[serde(default)]
+    pub verified_by: Option<User>,
+    #[serde(default)]
+    pub date: Option<DateValue>,
+}
+
+/// Result of a formula, by result type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FormulaValue {
+    String {
+        string: Option<String>,
+    },
+    Number {
+        number: Option<f64>,
+    },
+    Boolean {
+        boolean: Option<bool>,
+    },
+    Date {
+        date: Option<DateValue>,
+    },
+    /// A result type this crate does not model
+    #[serde(other)]
+    Unknown,
+}
+
+/// Result of a rollup, by result type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RollupValue {
+    Number {
+        number: Option<f64>,
+    },
+    Date {
+        date: Option<DateValue>,
+    },
+    /// Values of the rolled up property, for `show_original` and similar functions
+    Array {
+        array: Vec<PropertyValue>,
+    },
+    /// A result type this crate does not model
+    #[serde(other)]
+    Unknown,
+}
+
+/// Value of a page property, as returned in the `properties` of a page
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyValue {
+    Title(Vec<RichText>),
+    RichText(Vec<RichText>),
+    Number(Option<f64>),
+    Select(Option<SelectOption>),
+    MultiSelect(Vec<SelectOption>),
+    Status(Option<SelectOption>),
+    Date(Option<DateValue>),
+    People(Vec<User>),
+    Files(Vec<NamedFile>),
+    Checkbox(bool),
+    Url(Option<String>),
+    Email(Option<String>),
+    PhoneNumber(Option<String>),
+    Formula(FormulaValue),
+    Relation(Vec<PageReference>),
+    Rollup(RollupValue),
+    CreatedTime(String),
+    CreatedBy(User),
+    LastEditedTime(String),
+    LastEditedBy(User),
+    UniqueId(UniqueIdValue),
+    Verification(Option<VerificationValue>),
+    /// A property type this crate does not model, kept as it was returned
+    Unknown {
+        type_name: String,
+        content: Value,
+    },
+}
+
+impl PropertyValue {
+    /// Builds a value from a type name and the type-specific content
+    pub fn from_parts(type_name: &str, content: Value) -> Result<Self, serde_json::Error> {
+        use serde_json::from_value;
+        Ok(match type_name {
+            "title" => PropertyValue::Title(from_value(content)?),
+            "rich_text" => PropertyValue::RichText(from_value(content)?),
+            "number" => PropertyValue::Number(from_value(content)?),
+            "select" => PropertyValue::Select(from_value(content)?),
+            "multi_select" => PropertyValue::MultiSelect(from_value(content)?),
+            "status" => PropertyValue::Status(from_value(content)?),
+            "date" => PropertyValue::Date(from_value(content)?),
+            "people" => PropertyValue::People(from_value(content)?),
+            "files" => PropertyValue::Files(from_value(content)?),
+            "checkbox" => PropertyValue::Checkbox(from_value(content)?),
+            "url" => PropertyValue::Url(from_value(content)?),
+            "email" => PropertyValue::Email(from_value(content)?),
+            "phone_number" => PropertyValue::PhoneNumber(from_value(content)?),
+            "formula" => PropertyValue::Formula(from_value(content)?),
+            "relation" => PropertyValue::Relation(from_value(content)?),
+            "rollup" => PropertyValue::Rollup(from_value(content)?),
+            "created_time" => PropertyValue::CreatedTime(from_value(content)?),
+            "created_by" => PropertyValue::CreatedBy(from_value(content)?),
+            "last_edited_time" => PropertyValue::LastEditedTime(from_value(content)?),
+            "last_edited_by" => PropertyValue::LastEditedBy(from_value(content)?),
+            "unique_id" => PropertyValue::UniqueId(from_value(content)?),
+            "verification" => PropertyValue::Verification(from_value(content)?),
+            other => PropertyValue::Unknown {
+                type_name: other.to_string(),
+                content,
+            },
+        })
+    }
+
+    pub fn type_name(&self) -> &str {
+        match self {
+            PropertyValue::Title(_) => "title",
+            PropertyValue::RichText(_) => "rich_text",
+            PropertyValue::Number(_) => "number",
+            PropertyValue::Select(_) => "select",
+            PropertyValue::MultiSelect(_) => "multi_select",
+            PropertyValue::Status(_) => "status",
+            PropertyValue::Date(_) => "date",
+            PropertyValue::People(_) => "people",
+            PropertyValue::Files(_) => "files",
+            PropertyValue::Checkbox(_) => "checkbox",
+            PropertyValue::Url(_) => "url",
+            PropertyValue::Email(_) => "email",
+            PropertyValue::PhoneNumber(_) => "phone_number",
+            PropertyValue::Formula(_) => "formula",
+            PropertyValue::Relation(_) => "relation",
+            PropertyValue::Rollup(_) => "rollup",
+            PropertyValue::CreatedTime(_) => "created_time",
+            PropertyValue::CreatedBy(_) => "created_by",
+            PropertyValue::LastEditedTime(_) => "last_edited_time",
+            PropertyValue::LastEditedBy(_) => "last_edited_by",
+            PropertyValue::UniqueId(_) => "unique_id",
+            PropertyValue::Verification(_) => "verification",
+            PropertyValue::Unknown { type_name, .. } => type_name,
+        }
+    }
+
+    /// The type-specific content, e.g. `{ "name": "Done", ... }` for a select
+    pub fn content(&self) -> Value {
+        let content = match self {
+            PropertyValue::Title(text) | PropertyValue::RichText(text) => {
+                serde_json::to_value(text)
+            }
+            PropertyValue::Number(number) => serde_json::to_value(number),
+            PropertyValue::Select(option) | PropertyValue::Status(option) => {
+                serde_json::to_value(option)
+            }
+            PropertyValue::MultiSelect(options) => serde_json::to_value(options),
+            PropertyValue::Date(date) => serde_json::to_value(date),
+            PropertyValue::People(people) => serde_json::to_value(people),
+            PropertyValue::Files(files) => serde_json::to_value(files),
+            PropertyValue::Checkbox(checked) => serde_json::to_value(checked),
+            PropertyValue::Url(text)
+            | PropertyValue::Email(text)
+            | PropertyValue::PhoneNumber(text) => serde_json::to_value(text),
+            PropertyValue::Formula(formula) => serde_json::to_value(formula),
+            PropertyValue::Relation(pages) => serde_json::to_value(pages),
+            PropertyValue::Rollup(rollup) => serde_json::to_value(rollup),
+            PropertyValue::CreatedTime(time) | PropertyValue::LastEditedTime(time) => {
+                serde_json::to_value(time)
+            }
+            PropertyValue::CreatedBy(user) | PropertyValue::LastEditedBy(user) => {
+                serde_json::to_value(user)
+            }
+            PropertyValue::UniqueId(id) => serde_json::to_value(id),
+            PropertyValue::Verification(verification) => serde_json::to_value(verification),
+            PropertyValue::Unknown { content, .. } => Ok(content.clone()),
+        };
+        content.unwrap_or(Value::Null)
+    }
+
+    /// Plain text of a title or rich text value
+    pub fn as_text(&self) -> Option<String> {
+        match self {
+            PropertyValue::Title(text) | PropertyValue::RichText(text) => Some(plain_text(text)),
+            _ => None,
+        }
+    }
+
+    /// Text of a URL, email, phone number, timestamp or string formula value
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            PropertyValue::Url(text)
+            | PropertyValue::Email(text)
+            | PropertyValue::PhoneNumber(text)
+            | PropertyValue::Formula(FormulaValue::String { string: text }) => text.as_deref(),
+            PropertyValue::CreatedTime(time) | PropertyValue::LastEditedTime(time) => Some(time),
+            _ => None,
+        }
+    }
+
+    /// Number of a number, unique ID, or number formula or rollup value
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            PropertyValue::Number(number)
+            | PropertyValue::Formula(FormulaValue::Number { number })
+            | PropertyValue::Rollup(RollupValue::Number { number }) => *number,
+            PropertyValue::UniqueId(id) => id.number.map(|number| number as f64),
+            _ => None,
+        }
+    }
+
+    /// Whether a checkbox or boolean formula is checked
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            PropertyValue::Checkbox(checked) => Some(*checked),
+            PropertyValue::Formula(FormulaValue::Boolean { boolean }) => *boolean,
+            _ => None,
+        }
+    }
+
+    /// Date of a date, or date formula or rollup value
+    pub fn as_date(&self) -> Option<&DateValue> {
+        match self {
+            PropertyValue::Date(date)
+            | PropertyValue::Formula(FormulaValue::Date { date })
+            | PropertyValue::Rollup(RollupValue::Date { date }) => date.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Selected option of a select or status value
+    pub fn as_select(&self) -> Option<&SelectOption> {
+        match self {
+            PropertyValue::Select(option) | PropertyValue::Status(option) => option.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn as_multi_select(&self) -> Option<&[SelectOption]> {
+        match self {
+            PropertyValue::MultiSelect(options) => Some(options),
+            _ => None,
+        }
+    }
+
+    /// Users of a people value, or the user of a created by or last edited by value
+    pub fn as_people(&self) -> Option<&[User]> {
+        match self {
+            PropertyValue::People(people) => Some(people),
+            PropertyValue::CreatedBy(user) | PropertyValue::LastEditedBy(user) => {
+                Some(std::slice::from_ref(user))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_files(&self) -> Option<&[NamedFile]> {
+        match self {
+            PropertyValue::Files(files) => Some(files),
+            _ => None,
+        }
+    }
+
+    /// IDs of the related pages of a relation value
+    pub fn as_relation(&self) -> Option<Vec<&str>> {
+        match self {
+            PropertyValue::Relation(pages) => Some(pages.iter().map(|p| p.id.as_str()).collect()),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for PropertyValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let type_name = self.type_name();
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("type", type_name)?;
+        map.serialize_entry(type_name, &self.content())?;
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for PropertyValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut object = Map::deserialize(deserializer)?;
+        let type_name = match object.remove("type") {
+            Some(Value::String(type_name)) => type_name,
+            _ => return Err(de::Error::missing_field("type")),
+        };
+        let content = object.remove(&type_name).unwrap_or(Value::Null);
+        PropertyValue::from_parts(&type_name, content).map_err(de::Error::custom)
+    }
+}
+
+/// A rolled up item of an array rollup as an element of its `array`. Items
+/// of titles, rich text, relations and people hold a single entry, which the
+/// element holds as a list.
+fn rollup_element(mut item: Value) -> Value {
+    let type_name = item["type"].as_str().unwrap_or_default().to_string();
+    let mut content = item[&type_name].take();
+    if matches!(
+        type_name.as_str(),
+        "title" | "rich_text" | "relation" | "people"
+    ) && !content.is_array()
+    {
+        content = Value::Array(vec![content]);
+    }
+    let mut element = Map::new();
+    element.insert("type".to_string(), Value::String(type_name.clone()));
+    element.insert(type_name, content);
+    Value::Object(element)
+}
+
+/// Assembles the value of a paginated property from the `property_item`
+/// object of the list responses and every item of their `results`
+pub(crate) fn property_from_items(
+    property_item: &Value,
+    results: Vec<Value>,
+) -> Result<PropertyValue, serde_json::Error> {
+    let type_name = property_item["type"].as_str().unwrap_or_default();
+    if type_name == "rollup" {
+        // Other rollups are computed on the property item itself, while the
+        // items of an array rollup are the results
+        let mut rollup = property_item["rollup"].clone();
+        if rollup["type"] == "array" {
+            rollup["array"] = results.into_iter().map(rollup_element).collect();
+        }
+        return PropertyValue::from_parts(type_name, rollup);
+    }
+    let items = results
+        .into_iter()
+        .map(|mut item| item[type_name].take())
+        .collect();
+    PropertyValue::from_parts(type_name, Value::Array(items))
+}
+
+impl NotionClient {
+    /// Retrieves the complete value of one property of a page.
+    ///
+    /// Titles, rich text, relations, people and rollups are returned in
+    /// pages of items, which are all fetched and assembled into a single
+    /// value; other properties are returned directly.
+    pub async fn get_page_property(
+        &self,
+        page_id: &str,
+        property_id: &str,
+    ) -> Result<PropertyValue, NotionError> {
+        let invalid = |e: serde_json::Error| NotionError::InvalidProperty {
+            property: property_id.to_string(),
+            message: e.to_string(),
+        };
+        let mut response: Value = self
+            .get(Page::property_request(page_id, property_id, None))
+            .await?;
+        if response["object"] != "list" {
+            return serde_json::from_value(response).map_err(invalid);
+        }
+
+        let property_item = response["property_item"].take();
+        let first: ListResponse<Value> = serde_json::from_value(response).map_err(invalid)?;
+        let mut results = first.results;
+        if let Some(cursor) = first.next_cursor.filter(|_| first.has_more) {
+            let rest: Vec<Value> = self
+                .property_items_stream_from(page_id, property_id, Some(cursor))
+                .try_collect()
+                .await?;
+            results.extend(rest);
+        }
+        property_from_items(&property_item, results).map_err(invalid)
+    }
+}
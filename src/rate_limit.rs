@@ -0,0 +1,38 @@
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+
+/// Client-side limiter that spaces requests evenly to stay under the
+/// Notion API rate limit (an average of three requests per second)
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// Minimum interval between two requests
+    interval: Duration,
+    /// Earliest instant at which the next request may be sent
+    next_slot: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    /// Creates a limiter allowing the given number of requests per second
+    pub fn new(requests_per_second: u32) -> Self {
+        let requests_per_second = requests_per_second.max(1);
+        Self {
+            interval: Duration::from_secs(1) / requests_per_second,
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Waits until a request slot is available and reserves it
+    pub async fn acquire(&self) {
+        let wait = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            let now = Instant::now();
+            let slot = (*next_slot).max(now);
+            *next_slot = slot + self.interval;
+            slot - now
+        };
+        if !wait.is_zero() {
+            sleep(wait).await;
+        }
+    }
+}
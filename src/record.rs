@@ -0,0 +1,395 @@
+use futures::TryStreamExt;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+pub use serde_json::{Map, Value};
+
+use crate::client::NotionClient;
+use crate::database::{CreateDatabase, Database, DatabaseQuery};
+use crate::error::NotionError;
+use crate::import::{file_name, text_objects};
+use crate::page::Page;
+use crate::property::PropertyValue;
+use crate::response::ObjectResponse;
+use crate::schema::{is_read_only_type, PropertySchema};
+
+/// A Rust type stored as a row of a Notion database.
+///
+/// Usually derived with `#[derive(NotionRecord)]`:
+///
+/// ```ignore
+/// #[derive(NotionRecord)]
+/// struct Task {
+///     #[notion(name = "Name", type = "title")]
+///     name: String,
+///     #[notion(name = "Due", type = "date")]
+///     due: Option<String>,
+///     done: bool,
+/// }
+/// ```
+pub trait NotionRecord: Sized {
+    /// Database schema with one property per field, for `create_database`
+    fn schema() -> BTreeMap<String, PropertySchema>;
+
+    /// Page property values for creating or updating a page.
+    /// Read-only properties such as formulas are left out.
+    fn to_properties(&self) -> Result<Value, NotionError>;
+
+    /// Reads a record from the `properties` object of a page
+    fn from_properties(properties: &Value) -> Result<Self, NotionError>;
+
+    /// Reads a record from a page returned by the API
+    fn from_page(page: &Page) -> Result<Self, NotionError> {
+        Self::from_properties(&page.properties)
+    }
+}
+
+/// Conversion between a field type and a page property value
+pub trait PropertyField: Sized {
+    /// Property type used when the field does not specify one
+    fn default_type() -> &'static str;
+
+    /// The type-specific content of the property value, e.g. `{ "name": "Done" }` for a select
+    fn to_property_value(&self, property_type: &str) -> Result<Value, String>;
+
+    /// Reads the field from a page property object, or `Value::Null` if the property is missing
+    fn from_property_value(value: &Value) -> Result<Self, String>;
+}
+
+fn parts(value: &Value) -> Result<(&str, &Value), String> {
+    let kind = value["type"]
+        .as_str()
+        .ok_or_else(|| "property is missing".to_string())?;
+    Ok((kind, &value[kind]))
+}
+
+fn unsupported<T>(property_type: &str, rust_type: &str) -> Result<T, String> {
+    Err(format!(
+        "{} properties cannot be converted to or from {}",
+        property_type, rust_type
+    ))
+}
+
+impl PropertyField for String {
+    fn default_type() -> &'static str {
+        "rich_text"
+    }
+
+    fn to_property_value(&self, property_type: &str) -> Result<Value, String> {
+        Ok(match property_type {
+            "title" | "rich_text" => text_objects(self),
+            "select" | "status" => serde_json::json!({ "name": self }),
+            "url" | "email" | "phone_number" => Value::String(self.clone()),
+            "date" => serde_json::json!({ "start": self }),
+            other => return unsupported(other, "String"),
+        })
+    }
+
+    fn from_property_value(value: &Value) -> Result<Self, String> {
+        parts(value)?;
+        let property: PropertyValue =
+            serde_json::from_value(value.clone()).map_err(|e| e.to_string())?;
+        let text = match &property {
+            PropertyValue::Title(_) | PropertyValue::RichText(_) => property.as_text(),
+            PropertyValue::Select(_) | PropertyValue::Status(_) => {
+                property.as_select().map(|option| option.name.clone())
+            }
+            PropertyValue::Date(_) => property.as_date().map(|date| date.start.clone()),
+            PropertyValue::Verification(verification) => {
+                verification.as_ref().map(|v| v.state.clone())
+            }
+            PropertyValue::Formula(_) | PropertyValue::Rollup(_) => property
+                .as_str()
+                .map(str::to_string)
+                .or_else(|| property.as_date().map(|date| date.start.clone())),
+            PropertyValue::Url(_)
+            | PropertyValue::Email(_)
+            | PropertyValue::PhoneNumber(_)
+            | PropertyValue::CreatedTime(_)
+            | PropertyValue::LastEditedTime(_) => property.as_str().map(str::to_string),
+            other => return unsupported(other.type_name(), "String"),
+        };
+        text.ok_or_else(|| format!("{} is empty", property.type_name()))
+    }
+}
+
+impl PropertyField for f64 {
+    fn default_type() -> &'static str {
+        "number"
+    }
+
+    fn to_property_value(&self, property_type: &str) -> Result<Value, String> {
+        match property_type {
+            "number" => Ok(serde_json::json!(self)),
+            other => unsupported(other, "f64"),
+        }
+    }
+
+    fn from_property_value(value: &Value) -> Result<Self, String> {
+        let (kind, content) = parts(value)?;
+        let number = match kind {
+            "number" => content,
+            "formula" | "rollup" => &content["number"],
+            "unique_id" => &content["number"],
+            other => return unsupported(other, "f64"),
+        };
+        number.as_f64().ok_or_else(|| "number is empty".to_string())
+    }
+}
+
+impl PropertyField for i64 {
+    fn default_type() -> &'static str {
+        "number"
+    }
+
+    fn to_property_value(&self, property_type: &str) -> Result<Value, String> {
+        match property_type {
+            "number" => Ok(serde_json::json!(self)),
+            other => unsupported(other, "i64"),
+        }
+    }
+
+    fn from_property_value(value: &Value) -> Result<Self, String> {
+        let number = f64::from_property_value(value)?;
+        if number.fract() != 0.0 {
+            return Err(format!("{} is not an integer", number));
+        }
+        Ok(number as i64)
+    }
+}
+
+impl PropertyField for bool {
+    fn default_type() -> &'static str {
+        "checkbox"
+    }
+
+    fn to_property_value(&self, property_type: &str) -> Result<Value, String> {
+        match property_type {
+            "checkbox" => Ok(Value::Bool(*self)),
+            other => unsupported(other, "bool"),
+        }
+    }
+
+    fn from_property_value(value: &Value) -> Result<Self, String> {
+        let (kind, content) = parts(value)?;
+        let checked = match kind {
+            "checkbox" => content,
+            "formula" => &content["boolean"],
+            other => return unsupported(other, "bool"),
+        };
+        checked
+            .as_bool()
+            .ok_or_else(|| "checkbox is empty".to_string())
+    }
+}
+
+impl PropertyField for Vec<String> {
+    fn default_type() -> &'static str {
+        "multi_select"
+    }
+
+    /// Multi-select option names, or page and user IDs for relations and people
+    fn to_property_value(&self, property_type: &str) -> Result<Value, String> {
+        let items: Vec<Value> = match property_type {
+            "multi_select" => self
+                .iter()
+                .map(|name| serde_json::json!({ "name": name }))
+                .collect(),
+            "relation" | "people" => self
+                .iter()
+                .map(|id| serde_json::json!({ "id": id }))
+                .collect(),
+            "files" => self
+                .iter()
+                .map(|url| {
+                    serde_json::json!({ "name": file_name(url), "type": "external", "external": { "url": url } })
+                })
+                .collect(),
+            other => return unsupported(other, "Vec<String>"),
+        };
+        Ok(Value::Array(items))
+    }
+
+    fn from_property_value(value: &Value) -> Result<Self, String> {
+        let (kind, content) = parts(value)?;
+        let items = content.as_array().cloned().unwrap_or_default();
+        let field = |item: &Value| -> String {
+            let value = match kind {
+                "multi_select" => &item["name"],
+                "files" => {
+                    let file_type = item["type"].as_str().unwrap_or_default();
+                    &item[file_type]["url"]
+                }
+                _ => &item["id"],
+            };
+            value.as_str().unwrap_or_default().to_string()
+        };
+        match kind {
+            "multi_select" | "relation" | "people" | "files" => {
+                Ok(items.iter().map(field).collect())
+            }
+            other => unsupported(other, "Vec<String>"),
+        }
+    }
+}
+
+impl<T: PropertyField> PropertyField for Option<T> {
+    fn default_type() -> &'static str {
+        T::default_type()
+    }
+
+    fn to_property_value(&self, property_type: &str) -> Result<Value, String> {
+        match self {
+            Some(value) => value.to_property_value(property_type),
+            None => Ok(match property_type {
+                "title" | "rich_text" | "multi_select" | "relation" | "people" | "files" => {
+                    Value::Array(Vec::new())
+                }
+                _ => Value::Null,
+            }),
+        }
+    }
+
+    fn from_property_value(value: &Value) -> Result<Self, String> {
+        if value.is_null() {
+            return Ok(None);
+        }
+        let (kind, content) = parts(value)?;
+        let empty = match kind {
+            "title" | "rich_text" => content.as_array().map_or(true, Vec::is_empty),
+            "formula" | "rollup" => {
+                let result_type = content["type"].as_str().unwrap_or_default();
+                content[result_type].is_null()
+            }
+            _ => content.is_null(),
+        };
+        if empty {
+            Ok(None)
+        } else {
+            T::from_property_value(value).map(Some)
+        }
+    }
+}
+
+/// `PropertyField::to_property_value` for enums of select or status options
+/// that serialize to the option name, such as those emitted by `codegen`
+pub fn option_to_property_value<T: Serialize>(
+    option: &T,
+    property_type: &str,
+) -> Result<Value, String> {
+    match property_type {
+        "select" | "status" => serde_json::to_value(option)
+            .map(|name| serde_json::json!({ "name": name }))
+            .map_err(|e| e.to_string()),
+        other => unsupported(other, std::any::type_name::<T>()),
+    }
+}
+
+/// `PropertyField::from_property_value` for enums of select or status options
+/// that deserialize from the option name
+pub fn option_from_property_value<T: DeserializeOwned>(value: &Value) -> Result<T, String> {
+    let (kind, content) = parts(value)?;
+    match kind {
+        "select" | "status" => {
+            serde_json::from_value(content["name"].clone()).map_err(|e| e.to_string())
+        }
+        other => unsupported(other, std::any::type_name::<T>()),
+    }
+}
+
+/// Schema of a record field; `None` for rollups, which cannot be declared without
+/// knowing the related database
+pub fn field_schema<T: PropertyField>(
+    property_type: Option<&str>,
+    database_id: Option<&str>,
+    expression: Option<&str>,
+) -> Option<PropertySchema> {
+    let property_type = property_type.unwrap_or(T::default_type());
+    let config = match property_type {
+        "relation" => serde_json::json!({
+            "database_id": database_id.unwrap_or_default(),
+            "type": "single_property",
+            "single_property": {}
+        }),
+        "formula" => serde_json::json!({ "expression": expression.unwrap_or_default() }),
+        "rollup" => return None,
+        _ => Value::Object(Map::new()),
+    };
+    PropertySchema::from_parts(property_type, config).ok()
+}
+
+/// Adds the property value of a record field, skipping read-only properties
+pub fn insert_property<T: PropertyField>(
+    properties: &mut Map<String, Value>,
+    name: &str,
+    property_type: Option<&str>,
+    field: &T,
+) -> Result<(), NotionError> {
+    let property_type = property_type.unwrap_or(T::default_type());
+    if is_read_only_type(property_type) {
+        return Ok(());
+    }
+    let content =
+        field
+            .to_property_value(property_type)
+            .map_err(|message| NotionError::InvalidProperty {
+                property: name.to_string(),
+                message,
+            })?;
+    let mut value = Map::new();
+    value.insert(property_type.to_string(), content);
+    properties.insert(name.to_string(), Value::Object(value));
+    Ok(())
+}
+
+/// Reads a record field from the `properties` object of a page
+pub fn read_property<T: PropertyField>(properties: &Value, name: &str) -> Result<T, NotionError> {
+    T::from_property_value(&properties[name]).map_err(|message| NotionError::InvalidProperty {
+        property: name.to_string(),
+        message,
+    })
+}
+
+impl NotionClient {
+    /// Creates a database whose schema matches the record type
+    pub async fn create_database_for<T: NotionRecord>(
+        &self,
+        parent_page_id: &str,
+        title: &str,
+    ) -> Result<ObjectResponse<Database>, NotionError> {
+        let database = T::schema().iter().fold(
+            CreateDatabase::new(parent_page_id).title(title),
+            |database, (name, schema)| database.property(name, schema),
+        );
+        self.create_database_with(database).await
+    }
+
+    /// Creates a page in a database from a record
+    pub async fn create_record<T: NotionRecord>(
+        &self,
+        database_id: &str,
+        record: &T,
+    ) -> Result<ObjectResponse<Page>, NotionError> {
+        let properties = record.to_properties()?;
+        self.post(Page::create_in_database_request(database_id, properties))
+            .await
+    }
+
+    /// Queries a database and reads every matching page as a record.
+    /// Relation and people lists longer than the 25 items a page object holds
+    /// are completed first.
+    pub async fn query_records<T: NotionRecord>(
+        &self,
+        database_id: &str,
+        query: DatabaseQuery,
+    ) -> Result<Vec<T>, NotionError> {
+        self.query_database_stream(database_id, query)
+            .and_then(|mut page| async move {
+                self.complete_list_properties(&mut page).await?;
+                T::from_page(&page)
+            })
+            .try_collect()
+            .await
+    }
+}
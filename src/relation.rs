@@ -0,0 +1,229 @@
+use futures::{Stream, TryStreamExt};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::future::Future;
+
+use crate::client::NotionClient;
+use crate::database::id_key;
+use crate::error::NotionError;
+use crate::page::Page;
+use crate::pagination::paginate;
+use crate::response::ListResponse;
+
+/// Number of items Notion includes in relation and people values of page objects
+pub const INLINE_ITEM_LIMIT: usize = 25;
+
+/// Whether a relation or people value of a page object may be missing items
+fn is_truncated(value: &Value) -> bool {
+    let kind = value["type"].as_str().unwrap_or_default();
+    value["has_more"].as_bool().unwrap_or_else(|| {
+        value[kind]
+            .as_array()
+            .is_some_and(|items| items.len() >= INLINE_ITEM_LIMIT)
+    })
+}
+
+/// Replaces truncated relation and people values of a page with the items
+/// `fetch` returns for the page ID and property ID, as `property_item`
+/// objects
+pub(crate) async fn complete_list_properties_with<F, Fut>(
+    page: &mut Page,
+    mut fetch: F,
+) -> Result<(), NotionError>
+where
+    F: FnMut(String, String) -> Fut,
+    Fut: Future<Output = Result<Vec<Value>, NotionError>>,
+{
+    let Some(properties) = page.properties.as_object_mut() else {
+        return Ok(());
+    };
+    for value in properties.values_mut() {
+        let kind = value["type"].as_str().unwrap_or_default().to_string();
+        if !matches!(kind.as_str(), "relation" | "people") || !is_truncated(value) {
+            continue;
+        }
+        let Some(property_id) = value["id"].as_str().map(str::to_string) else {
+            continue;
+        };
+        let items = fetch(page.id.clone(), property_id).await?;
+        value[&kind] = items
+            .into_iter()
+            .map(|mut item| item[&kind].take())
+            .collect();
+        value["has_more"] = Value::Bool(false);
+    }
+    Ok(())
+}
+
+/// IDs of the related pages of every relation property of a page, by
+/// property name
+pub fn relation_ids(page: &Page) -> BTreeMap<String, Vec<String>> {
+    page.properties
+        .as_object()
+        .map(|properties| {
+            properties
+                .iter()
+                .filter(|(_, value)| value["type"] == "relation")
+                .map(|(name, value)| {
+                    let ids = value["relation"]
+                        .as_array()
+                        .map(|items| {
+                            items
+                                .iter()
+                                .filter_map(|item| item["id"].as_str().map(str::to_string))
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    (name.clone(), ids)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// One relation from a page to another
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelationEdge {
+    /// ID of the page holding the relation property
+    pub from: String,
+    /// Name of the relation property
+    pub property: String,
+    /// ID of the related page
+    pub to: String,
+}
+
+/// Pages reached by following relations from a root page
+#[derive(Debug, Clone, Default)]
+pub struct RelationGraph {
+    /// ID of the page the traversal started from
+    pub root: String,
+    /// Fetched pages by ID, with complete relation and people values
+    pub pages: HashMap<String, Page>,
+    /// Every relation of the fetched pages, including those pointing to pages
+    /// that were not fetched because they are beyond the maximum depth
+    pub edges: Vec<RelationEdge>,
+}
+
+impl RelationGraph {
+    /// Fetched pages related to a page through a property
+    pub fn related(&self, page_id: &str, property: &str) -> Vec<&Page> {
+        self.edges
+            .iter()
+            .filter(|edge| edge.from == page_id && edge.property == property)
+            .filter_map(|edge| self.pages.get(&edge.to))
+            .collect()
+    }
+}
+
+/// Options for `NotionClient::traverse_relations`
+#[derive(Debug, Clone, Default)]
+pub struct TraversalOptions {
+    /// How many relation hops to fetch pages for. With 0 only the root page is
+    /// fetched, with 1 the pages it relates to as well, and so on.
+    pub depth: usize,
+    /// Relation properties to follow, by name. All of them when `None`.
+    pub properties: Option<Vec<String>>,
+}
+
+impl NotionClient {
+    /// Streams every item of a paginated property value (relation, people,
+    /// rich text or title) as `property_item` objects
+    pub fn property_items_stream<'a>(
+        &'a self,
+        page_id: &'a str,
+        property_id: &'a str,
+    ) -> impl Stream<Item = Result<Value, NotionError>> + 'a {
+        self.property_items_stream_from(page_id, property_id, None)
+    }
+
+    /// Streams the items of a paginated property value starting at `cursor`,
+    /// or at the first item when it is `None`
+    pub(crate) fn property_items_stream_from<'a>(
+        &'a self,
+        page_id: &'a str,
+        property_id: &'a str,
+        cursor: Option<String>,
+    ) -> impl Stream<Item = Result<Value, NotionError>> + 'a {
+        paginate(move |next| {
+            let cursor = next.or_else(|| cursor.clone());
+            async move {
+                self.get::<ListResponse<Value>>(Page::property_request(
+                    page_id,
+                    property_id,
+                    cursor.as_deref(),
+                ))
+                .await
+            }
+        })
+    }
+
+    /// Replaces truncated relation and people values of a page with their
+    /// complete lists, fetched from the property item endpoint
+    pub async fn complete_list_properties(&self, page: &mut Page) -> Result<(), NotionError> {
+        complete_list_properties_with(page, |page_id, property_id| async move {
+            self.property_items_stream(&page_id, &property_id)
+                .try_collect()
+                .await
+        })
+        .await
+    }
+
+    /// Related page IDs of every relation property of a page, by property
+    /// name, without the 25 item limit of page objects
+    pub async fn resolve_relations(
+        &self,
+        page: &Page,
+    ) -> Result<BTreeMap<String, Vec<String>>, NotionError> {
+        let mut page = page.clone();
+        self.complete_list_properties(&mut page).await?;
+        Ok(relation_ids(&page))
+    }
+
+    /// Fetches a page and the pages it relates to, breadth first, up to
+    /// `options.depth` hops away.
+    ///
+    /// Each page is fetched once, so cycles between pages end the traversal
+    /// instead of repeating it; the edges closing a cycle are still recorded.
+    pub async fn traverse_relations(
+        &self,
+        page_id: &str,
+        options: TraversalOptions,
+    ) -> Result<RelationGraph, NotionError> {
+        let mut graph = RelationGraph {
+            root: page_id.to_string(),
+            ..Default::default()
+        };
+        // IDs are compared without dashes, which callers may leave out
+        let mut queued = HashSet::from([id_key(page_id)]);
+        let mut queue = VecDeque::from([(page_id.to_string(), 0)]);
+
+        while let Some((id, depth)) = queue.pop_front() {
+            let response = self.get_page(&id).await?;
+            let mut page = response.data;
+            self.complete_list_properties(&mut page).await?;
+
+            for (property, related) in relation_ids(&page) {
+                if let Some(followed) = &options.properties {
+                    if !followed.contains(&property) {
+                        continue;
+                    }
+                }
+                for to in related {
+                    if depth < options.depth && queued.insert(id_key(&to)) {
+                        queue.push_back((to.clone(), depth + 1));
+                    }
+                    graph.edges.push(RelationEdge {
+                        from: page.id.clone(),
+                        property: property.clone(),
+                        to,
+                    });
+                }
+            }
+            if id == graph.root {
+                graph.root = page.id.clone();
+            }
+            graph.pages.insert(page.id.clone(), page);
+        }
+        Ok(graph)
+    }
+}
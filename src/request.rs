@@ -1,6 +1,5 @@
 use serde::Serialize;
 use serde_json::Value;
-use std::collections::HashMap;
 
 /// Builder for constructing Notion API requests
 pub struct RequestBuilder {
@@ -8,8 +7,8 @@ pub struct RequestBuilder {
     endpoint: String,
     /// HTTP method for the request
     method: String,
-    /// Query parameters for the request
-    query_params: HashMap<String, String>,
+    /// Query parameters for the request, in insertion order
+    query_params: Vec<(String, String)>,
     /// JSON body for POST/PATCH requests
     body: Option<Value>,
 }
@@ -20,18 +19,31 @@ impl RequestBuilder {
         Self {
             endpoint: endpoint.to_string(),
             method: "GET".to_string(),
-            query_params: HashMap::new(),
+            query_params: Vec::new(),
             body: None,
         }
     }
 
-    /// Adds a query parameter to the request
+    /// Adds a query parameter to the request, replacing any previous value for the key
     pub fn query_param<K, V>(mut self, key: K, value: V) -> Self
     where
         K: ToString,
         V: ToString,
     {
-        self.query_params.insert(key.to_string(), value.to_string());
+        let key = key.to_string();
+        self.query_params.retain(|(k, _)| *k != key);
+        self.query_params.push((key, value.to_string()));
+        self
+    }
+
+    /// Adds a query parameter to the request, keeping previous values for the key.
+    /// Used for list parameters such as `filter_properties`.
+    pub fn append_query_param<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: ToString,
+        V: ToString,
+    {
+        self.query_params.push((key.to_string(), value.to_string()));
         self
     }
 
@@ -43,7 +55,7 @@ impl RequestBuilder {
         I: IntoIterator<Item = (K, V)>,
     {
         for (key, value) in params {
-            self.query_params.insert(key.to_string(), value.to_string());
+            self = self.query_param(key, value);
         }
         self
     }
@@ -52,7 +52,7 @@ pub struct BaseProperties {
 }
 
 /// Represents a user in Notion
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct User {
     /// Unique identifier of the user
     pub id: String,
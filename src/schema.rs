@@ -0,0 +1,484 @@
+use serde::de::{self, Deserializer};
+use serde::ser::{SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// A property in a database schema, as returned under `Database::properties`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatabaseProperty {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Type of the property and its type-specific configuration
+    #[serde(flatten)]
+    pub schema: PropertySchema,
+}
+
+/// Type and configuration of a database property
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertySchema {
+    Title,
+    RichText,
+    Number(NumberConfig),
+    Select(SelectConfig),
+    MultiSelect(SelectConfig),
+    Status(StatusConfig),
+    Date,
+    People,
+    Files,
+    Checkbox,
+    Url,
+    Email,
+    PhoneNumber,
+    Formula(FormulaConfig),
+    Relation(RelationConfig),
+    Rollup(RollupConfig),
+    CreatedTime,
+    CreatedBy,
+    LastEditedTime,
+    LastEditedBy,
+    UniqueId(UniqueIdConfig),
+    Verification,
+    /// A property type this crate does not model, kept as-is
+    Unknown {
+        type_name: String,
+        config: Value,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NumberConfig {
+    /// Display format such as `number`, `percent` or `dollar`
+    #[serde(default = "default_number_format")]
+    pub format: String,
+}
+
+fn default_number_format() -> String {
+    "number".to_string()
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SelectConfig {
+    #[serde(default)]
+    pub options: Vec<SelectOption>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StatusConfig {
+    #[serde(default)]
+    pub options: Vec<SelectOption>,
+    #[serde(default)]
+    pub groups: Vec<StatusGroup>,
+}
+
+/// An option of a select, multi-select or status property
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SelectOption {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl SelectOption {
+    /// Creates an option with the given name and the default color
+    pub fn new(name: &str) -> Self {
+        Self {
+            id: None,
+            name: name.to_string(),
+            color: None,
+            description: None,
+        }
+    }
+}
+
+/// The options to send for a select or multi-select property whose options
+/// are `current`. Options of `options` that already exist keep their ID and
+/// color unless they give their own.
+pub(crate) fn merge_select_options(
+    current: &[SelectOption],
+    options: &[SelectOption],
+) -> Vec<SelectOption> {
+    options
+        .iter()
+        .map(|option| {
+            let mut option = option.clone();
+            if let Some(existing) = current.iter().find(|o| o.name == option.name) {
+                option.id = option.id.or_else(|| existing.id.clone());
+                option.color = option.color.or_else(|| existing.color.clone());
+            }
+            option
+        })
+        .collect()
+}
+
+/// A group of status options (e.g. "To-do", "In progress", "Complete")
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusGroup {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub option_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormulaConfig {
+    pub expression: String,
+}
+
+/// Configuration of a relation property
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "RawRelationConfig", into = "RawRelationConfig")]
+pub struct RelationConfig {
+    /// Database the relation points to
+    pub database_id: String,
+    pub kind: RelationKind,
+}
+
+/// Whether a relation is one-way or mirrored by a property in the related database
+#[derive(Debug, Clone, PartialEq)]
+pub enum RelationKind {
+    Single,
+    Dual(DualProperty),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DualProperty {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub synced_property_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub synced_property_id: Option<String>,
+}
+
+/// Wire format of a relation, which nests the kind under a key named after it
+#[derive(Clone, Serialize, Deserialize)]
+struct RawRelationConfig {
+    database_id: String,
+    #[serde(rename = "type", default)]
+    kind: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    single_property: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dual_property: Option<DualProperty>,
+}
+
+impl From<RawRelationConfig> for RelationConfig {
+    fn from(raw: RawRelationConfig) -> Self {
+        let kind = match (raw.kind.as_deref(), raw.dual_property) {
+            (Some("dual_property"), dual) | (None, dual @ Some(_)) => {
+                RelationKind::Dual(dual.unwrap_or_default())
+            }
+            _ => RelationKind::Single,
+        };
+        Self {
+            database_id: raw.database_id,
+            kind,
+        }
+    }
+}
+
+impl From<RelationConfig> for RawRelationConfig {
+    fn from(config: RelationConfig) -> Self {
+        match config.kind {
+            RelationKind::Single => Self {
+                database_id: config.database_id,
+                kind: Some("single_property".to_string()),
+                single_property: Some(Value::Object(Map::new())),
+                dual_property: None,
+            },
+            RelationKind::Dual(dual) => Self {
+                database_id: config.database_id,
+                kind: Some("dual_property".to_string()),
+                single_property: None,
+                dual_property: Some(dual),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RollupConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relation_property_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relation_property_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rollup_property_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rollup_property_id: Option<String>,
+    /// Aggregation such as `count`, `sum` or `show_original`
+    pub function: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UniqueIdConfig {
+    #[serde(default)]
+    pub prefix: Option<String>,
+}
+
+impl PropertySchema {
+    /// Number property with the given display format
+    pub fn number(format: &str) -> Self {
+        PropertySchema::Number(NumberConfig {
+            format: format.to_string(),
+        })
+    }
+
+    /// Select property with the given option names
+    pub fn select(options: &[&str]) -> Self {
+        PropertySchema::Select(SelectConfig {
+            options: options.iter().map(|name| SelectOption::new(name)).collect(),
+        })
+    }
+
+    /// Multi-select property with the given option names
+    pub fn multi_select(options: &[&str]) -> Self {
+        PropertySchema::MultiSelect(SelectConfig {
+            options: options.iter().map(|name| SelectOption::new(name)).collect(),
+        })
+    }
+
+    /// Formula property computing the given expression
+    pub fn formula(expression: &str) -> Self {
+        PropertySchema::Formula(FormulaConfig {
+            expression: expression.to_string(),
+        })
+    }
+
+    /// One-way relation to another database
+    pub fn relation(database_id: &str) -> Self {
+        PropertySchema::Relation(RelationConfig {
+            database_id: database_id.to_string(),
+            kind: RelationKind::Single,
+        })
+    }
+
+    /// Two-way relation, mirrored by a property in the related database
+    pub fn dual_relation(database_id: &str) -> Self {
+        PropertySchema::Relation(RelationConfig {
+            database_id: database_id.to_string(),
+            kind: RelationKind::Dual(DualProperty::default()),
+        })
+    }
+
+    /// The property type name used by the Notion API
+    pub fn type_name(&self) -> &str {
+        match self {
+            PropertySchema::Title => "title",
+            PropertySchema::RichText => "rich_text",
+            PropertySchema::Number(_) => "number",
+            PropertySchema::Select(_) => "select",
+            PropertySchema::MultiSelect(_) => "multi_select",
+            PropertySchema::Status(_) => "status",
+            PropertySchema::Date => "date",
+            PropertySchema::People => "people",
+            PropertySchema::Files => "files",
+            PropertySchema::Checkbox => "checkbox",
+            PropertySchema::Url => "url",
+            PropertySchema::Email => "email",
+            PropertySchema::PhoneNumber => "phone_number",
+            PropertySchema::Formula(_) => "formula",
+            PropertySchema::Relation(_) => "relation",
+            PropertySchema::Rollup(_) => "rollup",
+            PropertySchema::CreatedTime => "created_time",
+            PropertySchema::CreatedBy => "created_by",
+            PropertySchema::LastEditedTime => "last_edited_time",
+            PropertySchema::LastEditedBy => "last_edited_by",
+            PropertySchema::UniqueId(_) => "unique_id",
+            PropertySchema::Verification => "verification",
+            PropertySchema::Unknown { type_name, .. } => type_name,
+        }
+    }
+
+    /// The type-specific configuration object
+    pub fn config(&self) -> Value {
+        let empty = || Value::Object(Map::new());
+        match self {
+            PropertySchema::Number(config) => serde_json::to_value(config).unwrap(),
+            PropertySchema::Select(config) | PropertySchema::MultiSelect(config) => {
+                serde_json::to_value(config).unwrap()
+            }
+            PropertySchema::Status(config) => serde_json::to_value(config).unwrap(),
+            PropertySchema::Formula(config) => serde_json::to_value(config).unwrap(),
+            PropertySchema::Relation(config) => serde_json::to_value(config).unwrap(),
+            PropertySchema::Rollup(config) => serde_json::to_value(config).unwrap(),
+            PropertySchema::UniqueId(config) => serde_json::to_value(config).unwrap(),
+            PropertySchema::Unknown { config, .. } => config.clone(),
+            _ => empty(),
+        }
+    }
+
+    /// The schema in the form expected by create and update database requests,
+    /// e.g. `{ "number": { "format": "percent" } }`
+    pub fn to_request_value(&self) -> Value {
+        let mut object = Map::new();
+        object.insert(self.type_name().to_string(), self.config());
+        Value::Object(object)
+    }
+
+    /// Whether values of this property are computed by Notion and cannot be written
+    pub fn is_read_only(&self) -> bool {
+        is_read_only_type(self.type_name())
+    }
+
+    /// Builds a schema from a type name and its configuration object
+    pub fn from_parts(type_name: &str, config: Value) -> Result<Self, serde_json::Error> {
+        let schema = match type_name {
+            "title" => PropertySchema::Title,
+            "rich_text" => PropertySchema::RichText,
+            "number" => PropertySchema::Number(serde_json::from_value(config)?),
+            "select" => PropertySchema::Select(serde_json::from_value(config)?),
+            "multi_select" => PropertySchema::MultiSelect(serde_json::from_value(config)?),
+            "status" => PropertySchema::Status(serde_json::from_value(config)?),
+            "date" => PropertySchema::Date,
+            "people" => PropertySchema::People,
+            "files" => PropertySchema::Files,
+            "checkbox" => PropertySchema::Checkbox,
+            "url" => PropertySchema::Url,
+            "email" => PropertySchema::Email,
+            "phone_number" => PropertySchema::PhoneNumber,
+            "formula" => PropertySchema::Formula(serde_json::from_value(config)?),
+            "relation" => PropertySchema::Relation(serde_json::from_value(config)?),
+            "rollup" => PropertySchema::Rollup(serde_json::from_value(config)?),
+            "created_time" => PropertySchema::CreatedTime,
+            "created_by" => PropertySchema::CreatedBy,
+            "last_edited_time" => PropertySchema::LastEditedTime,
+            "last_edited_by" => PropertySchema::LastEditedBy,
+            "unique_id" => PropertySchema::UniqueId(serde_json::from_value(config)?),
+            "verification" => PropertySchema::Verification,
+            other => PropertySchema::Unknown {
+                type_name: other.to_string(),
+                config,
+            },
+        };
+        Ok(schema)
+    }
+}
+
+/// Whether values of the named property type are computed or set by Notion and cannot be
+/// written through the API
+pub fn is_read_only_type(type_name: &str) -> bool {
+    matches!(
+        type_name,
+        "formula"
+            | "rollup"
+            | "created_time"
+            | "created_by"
+            | "last_edited_time"
+            | "last_edited_by"
+            | "unique_id"
+            | "verification"
+    )
+}
+
+impl Serialize for PropertySchema {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let type_name = self.type_name();
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("type", type_name)?;
+        map.serialize_entry(type_name, &self.config())?;
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for PropertySchema {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut object = Map::deserialize(deserializer)?;
+        let type_name = match object.remove("type") {
+            Some(Value::String(type_name)) => type_name,
+            _ => return Err(de::Error::missing_field("type")),
+        };
+        let config = object
+            .remove(&type_name)
+            .unwrap_or_else(|| Value::Object(Map::new()));
+        PropertySchema::from_parts(&type_name, config).map_err(de::Error::custom)
+    }
+}
+
+/// Reference to an existing database property by ID or by name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropertyRef {
+    Id(String),
+    Name(String),
+}
+
+impl PropertyRef {
+    pub fn id(id: &str) -> Self {
+        PropertyRef::Id(id.to_string())
+    }
+
+    pub fn name(name: &str) -> Self {
+        PropertyRef::Name(name.to_string())
+    }
+}
+
+impl std::fmt::Display for PropertyRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PropertyRef::Id(id) => write!(f, "property with id \"{}\"", id),
+            PropertyRef::Name(name) => write!(f, "property \"{}\"", name),
+        }
+    }
+}
+
+/// A typed change to an existing database property
+#[derive(Debug, Clone, PartialEq)]
+pub enum DatabasePropertyUpdate {
+    Rename {
+        property: PropertyRef,
+        new_name: String,
+    },
+    Remove {
+        property: PropertyRef,
+    },
+    ChangeType {
+        property: PropertyRef,
+        schema: PropertySchema,
+    },
+    /// Replaces the options of a select or multi-select property.
+    /// Options that already exist keep their ID and color unless overridden.
+    UpdateOptions {
+        property: PropertyRef,
+        options: Vec<SelectOption>,
+    },
+}
+
+impl DatabasePropertyUpdate {
+    pub fn rename(property: PropertyRef, new_name: &str) -> Self {
+        DatabasePropertyUpdate::Rename {
+            property,
+            new_name: new_name.to_string(),
+        }
+    }
+
+    pub fn remove(property: PropertyRef) -> Self {
+        DatabasePropertyUpdate::Remove { property }
+    }
+
+    pub fn change_type(property: PropertyRef, schema: PropertySchema) -> Self {
+        DatabasePropertyUpdate::ChangeType { property, schema }
+    }
+
+    pub fn update_options(property: PropertyRef, options: Vec<SelectOption>) -> Self {
+        DatabasePropertyUpdate::UpdateOptions { property, options }
+    }
+
+    /// The property this update applies to
+    pub fn property(&self) -> &PropertyRef {
+        match self {
+            DatabasePropertyUpdate::Rename { property, .. }
+            | DatabasePropertyUpdate::Remove { property }
+            | DatabasePropertyUpdate::ChangeType { property, .. }
+            | DatabasePropertyUpdate::UpdateOptions { property, .. } => property,
+        }
+    }
+}
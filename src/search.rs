@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::client::Page;
+use crate::database::Database;
+use crate::request::RequestBuilder;
+
+/// Parameters for the search endpoint
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SearchQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<u32>,
+}
+
+/// Object types the search endpoint can be restricted to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchObject {
+    Page,
+    Database,
+}
+
+impl SearchQuery {
+    /// Creates a query matching every page and database shared with the integration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restricts results to objects whose title contains the given text
+    pub fn query(mut self, query: &str) -> Self {
+        self.query = Some(query.to_string());
+        self
+    }
+
+    /// Restricts results to pages or databases only
+    pub fn object(mut self, object: SearchObject) -> Self {
+        let value = match object {
+            SearchObject::Page => "page",
+            SearchObject::Database => "database",
+        };
+        self.filter = Some(serde_json::json!({
+            "property": "object",
+            "value": value
+        }));
+        self
+    }
+
+    /// Sets the cursor to continue a previous search from
+    pub fn start_cursor(mut self, cursor: &str) -> Self {
+        self.start_cursor = Some(cursor.to_string());
+        self
+    }
+
+    /// Sets the number of results per page (at most 100)
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = Some(page_size);
+        self
+    }
+
+    pub fn request(&self) -> RequestBuilder {
+        RequestBuilder::new("/search")
+            .method("POST")
+            .json_body(serde_json::to_value(self).unwrap())
+    }
+}
+
+/// A single result of the search endpoint
+#[derive(Debug, Deserialize)]
+#[serde(tag = "object", rename_all = "snake_case")]
+pub enum SearchResult {
+    Page(Page),
+    Database(Database),
+}
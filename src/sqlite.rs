@@ -0,0 +1,493 @@
+use futures::StreamExt;
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::client::NotionClient;
+use crate::database::{plain_text, Database, DatabaseQuery};
+use crate::error::NotionError;
+use crate::export::{default_columns, flatten_property_value, list_items, CsvExportOptions};
+use crate::page::Page;
+use crate::schema::PropertySchema;
+
+/// Table recording which table mirrors each database and how far it is synced
+pub const SYNC_TABLE: &str = "_notion_sync";
+
+fn sqlite_error(error: rusqlite::Error) -> NotionError {
+    NotionError::Io(std::io::Error::new(std::io::ErrorKind::Other, error))
+}
+
+/// Quotes an identifier for use in SQL
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// Lowercase identifier made of ASCII letters, digits and underscores
+fn sanitize(name: &str) -> String {
+    let mut sanitized: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if sanitized.is_empty() || sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        sanitized.insert_str(0, "db_");
+    }
+    sanitized
+}
+
+/// Columns every mirrored table starts with
+const FIXED_COLUMNS: [&str; 3] = ["id", "created_time", "last_edited_time"];
+
+/// Property stored in the main table, with the name of its column, in column
+/// order. SQLite compares identifiers case-insensitively, so a property whose
+/// name matches a fixed column in any case is stored as `property_<name>`,
+/// and properties whose names differ only in case get a numeric suffix.
+fn property_columns(database: &Database) -> Vec<(&str, &PropertySchema, String)> {
+    let mut used: HashSet<String> = FIXED_COLUMNS.iter().map(|c| c.to_string()).collect();
+    let mut columns = Vec::new();
+    for name in default_columns(database) {
+        let Some((name, property)) = database.properties.get_key_value(&name) else {
+            continue;
+        };
+        if column_type(&property.schema).is_none() {
+            continue;
+        }
+        let mut column = if FIXED_COLUMNS.contains(&name.to_lowercase().as_str()) {
+            format!("property_{}", name)
+        } else {
+            name.clone()
+        };
+        let base = column.clone();
+        let mut suffix = 2;
+        while !used.insert(column.to_lowercase()) {
+            column = format!("{}_{}", base, suffix);
+            suffix += 1;
+        }
+        columns.push((name.as_str(), &property.schema, column));
+    }
+    columns
+}
+
+/// SQLite column type of a property, or `None` for relations, which are
+/// stored in join tables
+fn column_type(schema: &PropertySchema) -> Option<&'static str> {
+    match schema {
+        PropertySchema::Relation(_) => None,
+        PropertySchema::Number(_) => Some("REAL"),
+        PropertySchema::Checkbox => Some("INTEGER"),
+        _ => Some("TEXT"),
+    }
+}
+
+/// Converts a page property value into the value stored in its column.
+///
+/// Multi-selects, people and files are stored as JSON arrays of text, and
+/// dates as the start of the range.
+fn column_value(schema: &PropertySchema, value: &Value) -> SqlValue {
+    if value.is_null() {
+        return SqlValue::Null;
+    }
+    let number = |n: Option<f64>| n.map_or(SqlValue::Null, SqlValue::Real);
+    let text = |s: Option<&str>| s.map_or(SqlValue::Null, |s| SqlValue::Text(s.to_string()));
+    match schema {
+        PropertySchema::Number(_) => number(value["number"].as_f64()),
+        PropertySchema::Checkbox => value["checkbox"]
+            .as_bool()
+            .map_or(SqlValue::Null, |b| SqlValue::Integer(b as i64)),
+        PropertySchema::Date => text(value["date"]["start"].as_str()),
+        PropertySchema::MultiSelect(_) | PropertySchema::People | PropertySchema::Files => {
+            list_items(value).map_or(SqlValue::Null, |items| {
+                SqlValue::Text(json!(items).to_string())
+            })
+        }
+        _ => SqlValue::Text(flatten_property_value(
+            value,
+            &CsvExportOptions::default(),
+            &HashMap::new(),
+        )),
+    }
+}
+
+/// Result of syncing one database
+#[derive(Debug, Clone, Default)]
+pub struct DatabaseSyncReport {
+    pub database_id: String,
+    /// Table holding the pages of the database
+    pub table: String,
+    /// Pages inserted or updated
+    pub pages_written: usize,
+    /// Rows deleted because their page no longer matches, on full refresh only
+    pub pages_removed: usize,
+}
+
+/// Options for `NotionClient::sync_to_sqlite`
+#[derive(Debug, Clone, Default)]
+pub struct SyncOptions {
+    /// Fetch every page instead of only those edited since the last sync, and
+    /// delete rows of pages that were archived or deleted in Notion
+    pub full_refresh: bool,
+}
+
+/// Local SQLite copy of Notion databases.
+///
+/// Each database is mirrored into one table with an `id`, `created_time` and
+/// `last_edited_time` column followed by one column per property, named
+/// after the property. Since SQLite ignores case in column names, properties
+/// such as "ID" are stored as `property_ID`. Relation
+/// properties are stored in join tables named `<table>__<property>` with
+/// `page_id` and `related_page_id` columns.
+pub struct SqliteMirror {
+    connection: Connection,
+}
+
+impl SqliteMirror {
+    /// Opens or creates a mirror at the given path
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, NotionError> {
+        Self::with_connection(Connection::open(path).map_err(sqlite_error)?)
+    }
+
+    /// Creates a mirror that only lives in memory
+    pub fn in_memory() -> Result<Self, NotionError> {
+        Self::with_connection(Connection::open_in_memory().map_err(sqlite_error)?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self, NotionError> {
+        connection
+            .execute_batch(&format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                    database_id TEXT PRIMARY KEY,
+                    table_name TEXT NOT NULL UNIQUE,
+                    last_edited_time TEXT
+                )",
+                SYNC_TABLE
+            ))
+            .map_err(sqlite_error)?;
+        Ok(Self { connection })
+    }
+
+    /// Connection for running queries against the mirrored tables
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Name of the table mirroring a database, if it has been synced
+    pub fn table_name(&self, database_id: &str) -> Result<Option<String>, NotionError> {
+        self.connection
+            .query_row(
+                &format!(
+                    "SELECT table_name FROM {} WHERE database_id = ?1",
+                    SYNC_TABLE
+                ),
+                params![database_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sqlite_error)
+    }
+
+    /// Latest `last_edited_time` among the mirrored pages of a database
+    pub fn last_edited_time(&self, database_id: &str) -> Result<Option<String>, NotionError> {
+        self.connection
+            .query_row(
+                &format!(
+                    "SELECT last_edited_time FROM {} WHERE database_id = ?1",
+                    SYNC_TABLE
+                ),
+                params![database_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map(Option::flatten)
+            .map_err(sqlite_error)
+    }
+
+    /// Creates the tables for a database, adding columns for new properties.
+    /// Returns the name of the main table.
+    ///
+    /// The table is named after the database title when it is first created
+    /// and keeps that name when the database is renamed.
+    pub fn prepare_tables(&mut self, database: &Database) -> Result<String, NotionError> {
+        let table = match self.table_name(&database.id)? {
+            Some(table) => table,
+            None => {
+                let mut table = sanitize(&plain_text(&database.title));
+                let taken: bool = self
+                    .connection
+                    .query_row(
+                        &format!(
+                            "SELECT EXISTS(SELECT 1 FROM {} WHERE table_name = ?1)",
+                            SYNC_TABLE
+                        ),
+                        params![table],
+                        |row| row.get(0),
+                    )
+                    .map_err(sqlite_error)?;
+                if taken {
+                    table = format!("{}_{}", table, sanitize(&database.id.replace('-', "")));
+                }
+                self.connection
+                    .execute(
+                        &format!(
+                            "INSERT INTO {} (database_id, table_name) VALUES (?1, ?2)",
+                            SYNC_TABLE
+                        ),
+                        params![database.id, table],
+                    )
+                    .map_err(sqlite_error)?;
+                table
+            }
+        };
+
+        self.connection
+            .execute_batch(&format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                    id TEXT PRIMARY KEY,
+                    created_time TEXT,
+                    last_edited_time TEXT
+                )",
+                quote(&table)
+            ))
+            .map_err(sqlite_error)?;
+        let existing: HashSet<String> = self
+            .columns(&table)?
+            .iter()
+            .map(|column| column.to_lowercase())
+            .collect();
+        for (_, schema, column) in property_columns(database) {
+            if existing.contains(&column.to_lowercase()) {
+                continue;
+            }
+            self.connection
+                .execute_batch(&format!(
+                    "ALTER TABLE {} ADD COLUMN {} {}",
+                    quote(&table),
+                    quote(&column),
+                    column_type(schema).unwrap_or("TEXT")
+                ))
+                .map_err(sqlite_error)?;
+        }
+        for (name, property) in &database.properties {
+            if column_type(&property.schema).is_some() {
+                continue;
+            }
+            self.connection
+                .execute_batch(&format!(
+                    "CREATE TABLE IF NOT EXISTS {} (
+                        page_id TEXT NOT NULL,
+                        related_page_id TEXT NOT NULL,
+                        PRIMARY KEY (page_id, related_page_id)
+                    )",
+                    quote(&relation_table(&table, name))
+                ))
+                .map_err(sqlite_error)?;
+        }
+        Ok(table)
+    }
+
+    fn columns(&self, table: &str) -> Result<HashSet<String>, NotionError> {
+        let mut statement = self
+            .connection
+            .prepare(&format!("PRAGMA table_info({})", quote(table)))
+            .map_err(sqlite_error)?;
+        let columns = statement
+            .query_map([], |row| row.get(1))
+            .map_err(sqlite_error)?
+            .collect::<Result<_, _>>()
+            .map_err(sqlite_error)?;
+        Ok(columns)
+    }
+
+    /// Inserts or replaces pages of a database, creating its tables as needed.
+    /// Returns the name of the main table.
+    pub fn write_pages(
+        &mut self,
+        database: &Database,
+        pages: &[Page],
+    ) -> Result<String, NotionError> {
+        let table = self.prepare_tables(database)?;
+        let columns = property_columns(database);
+        let relations: Vec<&str> = database
+            .properties
+            .iter()
+            .filter(|(_, property)| column_type(&property.schema).is_none())
+            .map(|(name, _)| name.as_str())
+            .collect();
+
+        let transaction = self.connection.transaction().map_err(sqlite_error)?;
+        {
+            let names: Vec<String> = FIXED_COLUMNS
+                .into_iter()
+                .chain(columns.iter().map(|(_, _, column)| column.as_str()))
+                .map(quote)
+                .collect();
+            let placeholders: Vec<String> = (1..=names.len()).map(|i| format!("?{}", i)).collect();
+            let mut insert = transaction
+                .prepare(&format!(
+                    "INSERT OR REPLACE INTO {} ({}) VALUES ({})",
+                    quote(&table),
+                    names.join(", "),
+                    placeholders.join(", ")
+                ))
+                .map_err(sqlite_error)?;
+            for page in pages {
+                let mut values = vec![
+                    SqlValue::Text(page.id.clone()),
+                    SqlValue::Text(page.created_time.clone()),
+                    SqlValue::Text(page.last_edited_time.clone()),
+                ];
+                values.extend(
+                    columns
+                        .iter()
+                        .map(|(name, schema, _)| column_value(schema, &page.properties[*name])),
+                );
+                insert
+                    .execute(params_from_iter(values))
+                    .map_err(sqlite_error)?;
+            }
+
+            for name in &relations {
+                let join_table = quote(&relation_table(&table, name));
+                let mut delete = transaction
+                    .prepare(&format!("DELETE FROM {} WHERE page_id = ?1", join_table))
+                    .map_err(sqlite_error)?;
+                let mut insert = transaction
+                    .prepare(&format!(
+                        "INSERT OR IGNORE INTO {} (page_id, related_page_id) VALUES (?1, ?2)",
+                        join_table
+                    ))
+                    .map_err(sqlite_error)?;
+                for page in pages {
+                    delete.execute(params![page.id]).map_err(sqlite_error)?;
+                    for related in list_items(&page.properties[*name]).unwrap_or_default() {
+                        insert
+                            .execute(params![page.id, related])
+                            .map_err(sqlite_error)?;
+                    }
+                }
+            }
+
+            if let Some(latest) = pages.iter().map(|page| &page.last_edited_time).max() {
+                transaction
+                    .execute(
+                        &format!(
+                            "UPDATE {} SET last_edited_time = ?2
+                             WHERE database_id = ?1
+                             AND (last_edited_time IS NULL OR last_edited_time < ?2)",
+                            SYNC_TABLE
+                        ),
+                        params![database.id, latest],
+                    )
+                    .map_err(sqlite_error)?;
+            }
+        }
+        transaction.commit().map_err(sqlite_error)?;
+        Ok(table)
+    }
+
+    /// Deletes the rows of a database whose page ID is not in `keep`.
+    /// Returns the number of pages removed.
+    fn remove_pages_except(
+        &mut self,
+        database: &Database,
+        keep: &HashSet<String>,
+    ) -> Result<usize, NotionError> {
+        let table = self.prepare_tables(database)?;
+        let stale: Vec<String> = {
+            let mut statement = self
+                .connection
+                .prepare(&format!("SELECT id FROM {}", quote(&table)))
+                .map_err(sqlite_error)?;
+            let ids = statement
+                .query_map([], |row| row.get::<_, String>(0))
+                .map_err(sqlite_error)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(sqlite_error)?;
+            ids.into_iter().filter(|id| !keep.contains(id)).collect()
+        };
+
+        let mut tables = vec![(quote(&table), "id")];
+        tables.extend(
+            database
+                .properties
+                .iter()
+                .filter(|(_, property)| column_type(&property.schema).is_none())
+                .map(|(name, _)| (quote(&relation_table(&table, name)), "page_id")),
+        );
+        let transaction = self.connection.transaction().map_err(sqlite_error)?;
+        for (table, column) in &tables {
+            let mut delete = transaction
+                .prepare(&format!("DELETE FROM {} WHERE {} = ?1", table, column))
+                .map_err(sqlite_error)?;
+            for id in &stale {
+                delete.execute(params![id]).map_err(sqlite_error)?;
+            }
+        }
+        transaction.commit().map_err(sqlite_error)?;
+        Ok(stale.len())
+    }
+}
+
+fn relation_table(table: &str, property: &str) -> String {
+    format!("{}__{}", table, sanitize(property))
+}
+
+impl NotionClient {
+    /// Mirrors databases into SQLite.
+    ///
+    /// Only pages edited since the last sync are fetched unless
+    /// `options.full_refresh` is set. An incremental sync cannot see pages that
+    /// were archived or deleted in Notion; a full refresh removes them.
+    pub async fn sync_to_sqlite(
+        &self,
+        mirror: &mut SqliteMirror,
+        database_ids: &[&str],
+        options: SyncOptions,
+    ) -> Result<Vec<DatabaseSyncReport>, NotionError> {
+        let mut reports = Vec::with_capacity(database_ids.len());
+        for database_id in database_ids {
+            let database = self.get_database(database_id).await?.data;
+            let mut report = DatabaseSyncReport {
+                database_id: database.id.clone(),
+                table: mirror.prepare_tables(&database)?,
+                ..Default::default()
+            };
+
+            let mut query = DatabaseQuery::new().page_size(100);
+            if !options.full_refresh {
+                if let Some(since) = mirror.last_edited_time(&database.id)? {
+                    query = query.filter(json!({
+                        "timestamp": "last_edited_time",
+                        "last_edited_time": { "on_or_after": since }
+                    }));
+                }
+            }
+
+            let mut seen = HashSet::new();
+            let pages = self.query_database_stream(database_id, query).chunks(100);
+            futures::pin_mut!(pages);
+            while let Some(chunk) = pages.next().await {
+                let mut chunk = chunk.into_iter().collect::<Result<Vec<_>, _>>()?;
+                // Page objects list at most 25 related pages and people
+                for page in &mut chunk {
+                    self.complete_list_properties(page).await?;
+                }
+                mirror.write_pages(&database, &chunk)?;
+                report.pages_written += chunk.len();
+                seen.extend(chunk.into_iter().map(|page| page.id));
+            }
+            if options.full_refresh {
+                report.pages_removed = mirror.remove_pages_except(&database, &seen)?;
+            }
+            reports.push(report);
+        }
+        Ok(reports)
+    }
+}
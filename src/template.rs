@@ -0,0 +1,364 @@
+use futures::future::{BoxFuture, FutureExt};
+use serde_json::Value;
+
+use crate::block::BlockTree;
+use crate::client::NotionClient;
+use crate::copy::SameWorkspace;
+use crate::error::NotionError;
+use crate::page::{CreatePage, Page, Parent};
+use crate::page_move::moved_properties;
+use crate::response::ObjectResponse;
+
+/// A page whose properties and content contain `{{variables}}`.
+///
+/// A block whose whole text is `{{#name}}` starts a section that ends at a
+/// sibling block whose text is `{{/name}}`. The blocks in between are
+/// repeated for every item of the list `name`, with the fields of the item
+/// available as variables next to the outer ones, and `{{.}}` standing for
+/// the item itself. A section whose value is not a list is kept once when it
+/// is an object or `true`, and left out when it is `false` or `null`.
+///
+/// Code and equation blocks are copied as they are, so that they can contain
+/// braces of their own.
+#[derive(Debug, Clone)]
+pub struct PageTemplate {
+    pub page: Page,
+    pub blocks: Vec<BlockTree>,
+}
+
+fn template_error(message: String) -> NotionError {
+    NotionError::InvalidRequest(format!("Template: {}", message))
+}
+
+/// Value of a variable, looked up from the innermost scope outwards. Dotted
+/// names reach into objects.
+fn lookup<'v>(scopes: &[&'v Value], name: &str) -> Option<&'v Value> {
+    if name == "." {
+        return scopes.last().copied();
+    }
+    let mut path = name.split('.');
+    let first = path.next()?;
+    let value = scopes.iter().rev().find_map(|scope| scope.get(first))?;
+    path.try_fold(value, |value, key| value.get(key))
+}
+
+/// Every `{{name}}` in a text, as its byte range and the text it is replaced
+/// with
+fn placeholders(text: &str, scopes: &[&Value]) -> Result<Vec<(usize, usize, String)>, NotionError> {
+    let mut found = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find("{{").map(|start| offset + start) {
+        let Some(end) = text[start..].find("}}").map(|end| start + end) else {
+            break;
+        };
+        let name = text[start + 2..end].trim();
+        if name.starts_with(['#', '/']) {
+            return Err(template_error(format!(
+                "section marker \"{{{{{}}}}}\" must be a block of its own",
+                name
+            )));
+        }
+        let value = lookup(scopes, name)
+            .ok_or_else(|| template_error(format!("no value for \"{}\"", name)))?;
+        let replacement = match value {
+            Value::String(text) => text.clone(),
+            Value::Number(_) | Value::Bool(_) => value.to_string(),
+            Value::Null => String::new(),
+            _ => {
+                return Err(template_error(format!(
+                    "\"{}\" is a list or object and cannot be used as text",
+                    name
+                )))
+            }
+        };
+        found.push((start, end + 2, replacement));
+        offset = end + 2;
+    }
+    Ok(found)
+}
+
+/// Replaces every `{{name}}` in a text
+fn substitute(text: &str, scopes: &[&Value]) -> Result<String, NotionError> {
+    let mut result = String::with_capacity(text.len());
+    let mut position = 0;
+    for (start, end, replacement) in placeholders(text, scopes)? {
+        result.push_str(&text[position..start]);
+        result.push_str(&replacement);
+        position = end;
+    }
+    result.push_str(&text[position..]);
+    Ok(result)
+}
+
+/// Replaces the variables of a text, with `variables` as the only scope
+pub fn render_text(text: &str, variables: &Value) -> Result<String, NotionError> {
+    substitute(text, &[variables])
+}
+
+/// Replaces the variables in a rich text array. Notion splits text into
+/// several items where its formatting changes, so variables are looked for in
+/// the joined text of each run of text items, and replaced in the item they
+/// start in. Mentions and inline equations are kept as they are.
+fn substitute_rich_text(items: &mut Vec<Value>, scopes: &[&Value]) -> Result<(), NotionError> {
+    let is_text = |item: &Value| item["text"]["content"].is_string();
+    let mut start = 0;
+    while start < items.len() {
+        if !is_text(&items[start]) {
+            start += 1;
+            continue;
+        }
+        let end = (start..items.len())
+            .find(|&index| !is_text(&items[index]))
+            .unwrap_or(items.len());
+        let mut joined = String::new();
+        let mut ranges = Vec::new();
+        for item in &items[start..end] {
+            let content = item["text"]["content"].as_str().unwrap_or_default();
+            ranges.push((joined.len(), joined.len() + content.len()));
+            joined.push_str(content);
+        }
+        let found = placeholders(&joined, scopes)?;
+        for (item, (from, to)) in items[start..end].iter_mut().zip(ranges) {
+            let mut content = String::new();
+            let mut position = from;
+            for (first, last, replacement) in &found {
+                if *last <= from {
+                    continue;
+                }
+                if *first >= to {
+                    break;
+                }
+                if *first > position {
+                    content.push_str(&joined[position..*first]);
+                }
+                if *first >= from {
+                    content.push_str(replacement);
+                }
+                position = (*last).min(to);
+            }
+            if position < to {
+                content.push_str(&joined[position..to]);
+            }
+            if item.get("plain_text").is_some() {
+                item["plain_text"] = Value::String(content.clone());
+            }
+            item["text"]["content"] = Value::String(content);
+            if let Some(url) = item["text"]["link"]["url"].as_str() {
+                item["text"]["link"]["url"] = Value::String(substitute(url, scopes)?);
+            }
+        }
+        start = end;
+    }
+    // Items whose text was all part of a variable starting in an earlier one
+    items.retain(|item| !is_text(item) || item["text"]["content"] != "");
+    Ok(())
+}
+
+/// Whether a block type is kept as it is, without variables or section markers
+fn is_verbatim(block_type: &str) -> bool {
+    matches!(block_type, "code" | "equation")
+}
+
+/// Replaces the variables in the rich text and other strings of a block or
+/// property values, so that text, URLs and option names can all be
+/// templated
+fn substitute_strings(value: &mut Value, scopes: &[&Value]) -> Result<(), NotionError> {
+    match value {
+        Value::String(text) if text.contains("{{") => *text = substitute(text, scopes)?,
+        Value::Array(items) => {
+            for item in items {
+                substitute_strings(item, scopes)?;
+            }
+        }
+        Value::Object(fields) => {
+            if fields
+                .get("type")
+                .and_then(Value::as_str)
+                .is_some_and(is_verbatim)
+            {
+                return Ok(());
+            }
+            for (key, field) in fields.iter_mut() {
+                match field {
+                    Value::Array(items)
+                        if matches!(key.as_str(), "rich_text" | "title" | "caption") =>
+                    {
+                        substitute_rich_text(items, scopes)?
+                    }
+                    // Every cell of a table row is a rich text array
+                    Value::Array(cells) if key == "cells" => {
+                        for cell in cells.iter_mut().filter_map(Value::as_array_mut) {
+                            substitute_rich_text(cell, scopes)?;
+                        }
+                    }
+                    field => substitute_strings(field, scopes)?,
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Section marker of a block whose whole text is `{{#name}}` or
+/// `{{/name}}`: whether it opens the section, and its name
+fn marker(block: &BlockTree) -> Option<(bool, String)> {
+    let kind = block.value["type"]
+        .as_str()
+        .filter(|kind| !is_verbatim(kind))?;
+    let mut text = String::new();
+    for item in block.value[kind]["rich_text"].as_array()? {
+        let content = item["plain_text"]
+            .as_str()
+            .or_else(|| item["text"]["content"].as_str())?;
+        text.push_str(content);
+    }
+    let name = text.trim().strip_prefix("{{")?.strip_suffix("}}")?.trim();
+    match name.strip_prefix('#') {
+        Some(name) => Some((true, name.trim().to_string())),
+        None => Some((false, name.strip_prefix('/')?.trim().to_string())),
+    }
+}
+
+/// Substitutes variables in blocks and expands their sections
+fn render_blocks(blocks: &[BlockTree], scopes: &[&Value]) -> Result<Vec<BlockTree>, NotionError> {
+    let mut rendered = Vec::new();
+    let mut index = 0;
+    while index < blocks.len() {
+        let block = &blocks[index];
+        let name = match marker(block) {
+            None => {
+                let mut value = block.value.clone();
+                substitute_strings(&mut value, scopes)?;
+                let children = render_blocks(&block.children, scopes)?;
+                rendered.push(BlockTree { value, children });
+                index += 1;
+                continue;
+            }
+            Some((false, name)) => {
+                return Err(template_error(format!(
+                    "section \"{}\" is closed but was not opened",
+                    name
+                )))
+            }
+            Some((true, name)) => name,
+        };
+
+        // Sections of the same name may be nested
+        let mut depth = 0;
+        let mut end = None;
+        for (position, block) in blocks.iter().enumerate().skip(index + 1) {
+            match marker(block) {
+                Some((true, other)) if other == name => depth += 1,
+                Some((false, other)) if other == name && depth == 0 => {
+                    end = Some(position);
+                    break;
+                }
+                Some((false, other)) if other == name => depth -= 1,
+                _ => {}
+            }
+        }
+        let end =
+            end.ok_or_else(|| template_error(format!("section \"{}\" is not closed", name)))?;
+        let value = lookup(scopes, &name)
+            .ok_or_else(|| template_error(format!("no value for \"{}\"", name)))?;
+        let items: Vec<Option<&Value>> = match value {
+            Value::Array(items) => items.iter().map(Some).collect(),
+            Value::Object(_) => vec![Some(value)],
+            Value::Null | Value::Bool(false) => Vec::new(),
+            _ => vec![None],
+        };
+        for item in items {
+            let mut inner = scopes.to_vec();
+            inner.extend(item);
+            rendered.extend(render_blocks(&blocks[index + 1..end], &inner)?);
+        }
+        index = end + 1;
+    }
+    Ok(rendered)
+}
+
+impl PageTemplate {
+    /// The template with `variables` substituted in its properties and
+    /// blocks, and its sections expanded. `variables` is a JSON object whose
+    /// strings, numbers and booleans can be used as text, and whose lists
+    /// and objects drive sections.
+    pub fn render(&self, variables: &Value) -> Result<PageTemplate, NotionError> {
+        let mut page = self.page.clone();
+        substitute_strings(&mut page.properties, &[variables])?;
+        let blocks = render_blocks(&self.blocks, &[variables])?;
+        Ok(PageTemplate { page, blocks })
+    }
+}
+
+impl NotionClient {
+    /// Reads a page and its content as a template. Child pages and databases
+    /// in it are not part of the template. Files hosted by Notion are
+    /// uploaded again when a page is created from the template.
+    pub async fn load_template(&self, page_id: &str) -> Result<PageTemplate, NotionError> {
+        let mut page = self.get_page(page_id).await?.data;
+        self.complete_list_properties(&mut page).await?;
+        let blocks = self
+            .read_block_tree(page_id, &SameWorkspace { target: None }, &mut Vec::new())
+            .await?;
+        Ok(PageTemplate { page, blocks })
+    }
+
+    /// Creates a page from a template under a page or in a database.
+    ///
+    /// The title and, in a database, the properties it has with the same
+    /// name and type as the template page are set, along with the icon,
+    /// cover and content, with files hosted by Notion uploaded again. If the
+    /// content cannot be added, the page is moved to the trash rather than
+    /// left half rendered, and the error is returned.
+    pub async fn create_from_template(
+        &self,
+        template: &PageTemplate,
+        parent: Parent,
+        variables: &Value,
+    ) -> Result<ObjectResponse<Page>, NotionError> {
+        let rendered = template.render(variables)?;
+        let database = match &parent {
+            Parent::Database { database_id } => Some(self.get_database(database_id).await?.data),
+            _ => None,
+        };
+
+        let mut properties = Value::Object(moved_properties(&rendered.page, database.as_ref()));
+        self.upload_hosted_files(&mut properties).await?;
+        let mut blocks = rendered.blocks;
+        self.upload_block_files(&mut blocks).await?;
+        let mut create = CreatePage::new(parent).properties(properties);
+        if let Some(icon) = &rendered.page.icon {
+            if let Some(icon) = self.upload_hosted_icon(icon).await? {
+                create = create.icon(icon);
+            }
+        }
+        if let Some(cover) = &rendered.page.cover {
+            create = create.cover(self.upload_hosted_cover(cover).await?);
+        }
+        let created = self.create_page_with(create).await?;
+        if let Err(error) = self.append_block_tree(&created.data.id, &blocks).await {
+            let _ = self
+                .patch::<ObjectResponse<Page>>(Page::archive_request(&created.data.id, true))
+                .await;
+            return Err(error);
+        }
+        Ok(created)
+    }
+
+    /// Uploads the files hosted by Notion in blocks and their descendants
+    /// again, since the URLs the template was read with expire
+    fn upload_block_files<'a>(
+        &'a self,
+        blocks: &'a mut [BlockTree],
+    ) -> BoxFuture<'a, Result<(), NotionError>> {
+        async move {
+            for block in blocks {
+                self.upload_hosted_files(&mut block.value).await?;
+                self.upload_block_files(&mut block.children).await?;
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+}
@@ -0,0 +1,246 @@
+use futures::future::{BoxFuture, FutureExt};
+use reqwest::multipart::{Form, Part};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::client::NotionClient;
+use crate::error::NotionError;
+use crate::file::{File, Icon};
+use crate::request::RequestBuilder;
+
+/// Largest file that can be sent in a single part
+pub const MAX_SINGLE_PART_SIZE: usize = 20 * 1024 * 1024;
+
+/// Size of the parts larger files are sent in
+pub const PART_SIZE: usize = 10 * 1024 * 1024;
+
+/// File upload object returned by the Notion API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileUpload {
+    pub id: String,
+    /// `pending` until all content is sent, then `uploaded`; `expired` or
+    /// `failed` otherwise
+    pub status: String,
+    #[serde(default)]
+    pub filename: Option<String>,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    #[serde(default)]
+    pub content_length: Option<u64>,
+}
+
+impl FileUpload {
+    /// Request starting an upload, sent in `number_of_parts` parts when
+    /// there is more than one
+    pub fn create_request(
+        filename: &str,
+        content_type: &str,
+        number_of_parts: usize,
+    ) -> RequestBuilder {
+        let mut body = json!({
+            "mode": "single_part",
+            "filename": filename,
+            "content_type": content_type,
+        });
+        if number_of_parts > 1 {
+            body["mode"] = json!("multi_part");
+            body["number_of_parts"] = json!(number_of_parts);
+        }
+        RequestBuilder::new("/file_uploads")
+            .method("POST")
+            .json_body(body)
+    }
+
+    /// Endpoint the content is sent to, as a multipart form
+    pub fn send_request(file_upload_id: &str) -> RequestBuilder {
+        RequestBuilder::new(&format!("/file_uploads/{}/send", file_upload_id)).method("POST")
+    }
+
+    /// Request finishing an upload sent in several parts
+    pub fn complete_request(file_upload_id: &str) -> RequestBuilder {
+        RequestBuilder::new(&format!("/file_uploads/{}/complete", file_upload_id))
+            .method("POST")
+            .json_body(json!({}))
+    }
+
+    /// The uploaded file, to attach to a page, block or files property
+    pub fn file(&self) -> File {
+        File::uploaded(&self.id)
+    }
+}
+
+/// Decoded last segment of the path of a URL, used as the name of a file
+/// downloaded from it
+pub(crate) fn file_name_from_url(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let segment = path.rsplit('/').next().unwrap_or_default().as_bytes();
+    let mut bytes = Vec::with_capacity(segment.len());
+    let mut i = 0;
+    while i < segment.len() {
+        let hex = segment
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (segment[i], hex) {
+            (b'%', Some(byte)) => {
+                bytes.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                bytes.push(byte);
+                i += 1;
+            }
+        }
+    }
+    let name = String::from_utf8_lossy(&bytes).into_owned();
+    if name.is_empty() {
+        "file".to_string()
+    } else {
+        name
+    }
+}
+
+/// Whether a file object of a block or files property is hosted by Notion
+fn is_hosted_file(value: &Value) -> bool {
+    value["type"] == "file" && value["file"]["url"].is_string()
+}
+
+/// Replaces the hosted file of a file object with `reference`, of type `kind`
+fn replace_hosted_file(value: &mut Value, kind: &str, reference: Value) {
+    if let Some(fields) = value.as_object_mut() {
+        fields.remove("file");
+        fields.insert("type".to_string(), json!(kind));
+        fields.insert(kind.to_string(), reference);
+    }
+}
+
+/// Links every file hosted by Notion in a request value by its current URL,
+/// which expires after an hour
+pub(crate) fn link_hosted_files(value: &mut Value) {
+    if is_hosted_file(value) {
+        let url = value["file"]["url"].take();
+        replace_hosted_file(value, "external", json!({ "url": url }));
+        return;
+    }
+    match value {
+        Value::Array(items) => items.iter_mut().for_each(link_hosted_files),
+        Value::Object(fields) => fields.values_mut().for_each(link_hosted_files),
+        _ => {}
+    }
+}
+
+impl NotionClient {
+    /// Uploads every file hosted by Notion in a request value again, such as
+    /// a copied block or property value, and attaches the uploads instead.
+    /// Hosted file URLs expire after an hour, so they cannot be linked.
+    pub(crate) fn upload_hosted_files<'a>(
+        &'a self,
+        value: &'a mut Value,
+    ) -> BoxFuture<'a, Result<(), NotionError>> {
+        async move {
+            if is_hosted_file(value) {
+                let url = value["file"]["url"].as_str().unwrap_or_default();
+                let upload = self.upload_from_url(url).await?;
+                replace_hosted_file(value, "file_upload", json!({ "id": upload.id }));
+                return Ok(());
+            }
+            match value {
+                Value::Array(items) => {
+                    for item in items {
+                        self.upload_hosted_files(item).await?;
+                    }
+                }
+                Value::Object(fields) => {
+                    for field in fields.values_mut() {
+                        self.upload_hosted_files(field).await?;
+                    }
+                }
+                _ => {}
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+
+    /// An icon as it can be set on another page or database, uploaded again
+    /// when it is hosted by Notion. `None` for icon types this crate does
+    /// not model.
+    pub(crate) async fn upload_hosted_icon(
+        &self,
+        icon: &Icon,
+    ) -> Result<Option<Icon>, NotionError> {
+        Ok(match icon {
+            Icon::File { file } => Some(Icon::uploaded(&self.upload_from_url(&file.url).await?.id)),
+            icon => icon.linked(),
+        })
+    }
+
+    /// A cover as it can be set on another page or database, uploaded again
+    /// when it is hosted by Notion
+    pub(crate) async fn upload_hosted_cover(&self, cover: &File) -> Result<File, NotionError> {
+        Ok(match cover {
+            File::File { file } => self.upload_from_url(&file.url).await?.file(),
+            cover => cover.clone(),
+        })
+    }
+
+    /// Uploads a file with the file upload API, in parts of `PART_SIZE` when
+    /// it is larger than `MAX_SINGLE_PART_SIZE`. The upload can then be
+    /// attached through `FileUpload::file` within an hour.
+    pub async fn upload_file(
+        &self,
+        filename: &str,
+        content_type: &str,
+        content: &[u8],
+    ) -> Result<FileUpload, NotionError> {
+        let parts: Vec<&[u8]> = if content.len() > MAX_SINGLE_PART_SIZE {
+            content.chunks(PART_SIZE).collect()
+        } else {
+            vec![content]
+        };
+        let mut upload: FileUpload = self
+            .post(FileUpload::create_request(
+                filename,
+                content_type,
+                parts.len(),
+            ))
+            .await?;
+
+        for (index, part) in parts.iter().enumerate() {
+            let form = || {
+                let file = Part::bytes(part.to_vec())
+                    .file_name(filename.to_string())
+                    .mime_str(content_type)?;
+                let form = Form::new().part("file", file);
+                Ok(if parts.len() > 1 {
+                    form.text("part_number", (index + 1).to_string())
+                } else {
+                    form
+                })
+            };
+            upload = self
+                .post_multipart(FileUpload::send_request(&upload.id), form)
+                .await?;
+        }
+        if parts.len() > 1 {
+            upload = self.post(FileUpload::complete_request(&upload.id)).await?;
+        }
+        Ok(upload)
+    }
+
+    /// Downloads a file and uploads it with the file upload API, named after
+    /// the last segment of the URL path. Used to carry files hosted by Notion,
+    /// whose URLs expire, over to another workspace.
+    pub async fn upload_from_url(&self, url: &str) -> Result<FileUpload, NotionError> {
+        let (content, content_type) = self.download(url).await?;
+        // Drop parameters such as the charset
+        let content_type = content_type
+            .as_deref()
+            .and_then(|content_type| content_type.split(';').next())
+            .map(str::trim)
+            .filter(|content_type| !content_type.is_empty())
+            .unwrap_or("application/octet-stream");
+        self.upload_file(&file_name_from_url(url), content_type, &content)
+            .await
+    }
+}
@@ -0,0 +1,216 @@
+use serde_json::{json, Map, Value};
+
+use crate::client::NotionClient;
+use crate::database::{Database, DatabaseQuery};
+use crate::error::NotionError;
+use crate::import::text_objects;
+use crate::page::Page;
+use crate::response::ObjectResponse;
+use crate::schema::PropertySchema;
+
+/// What `NotionClient::upsert_page` did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpsertOutcome {
+    /// No page had the key, so one was created
+    Created,
+    /// A page had the key and some of the given properties differed
+    Updated,
+    /// A page had the key and all of the given properties already matched
+    Unchanged,
+}
+
+/// Result of upserting one page
+#[derive(Debug, Clone)]
+pub struct UpsertResult {
+    pub outcome: UpsertOutcome,
+    /// The created or updated page, or the existing page when unchanged
+    pub page: Page,
+}
+
+/// Whether every field of `expected` (a property value as sent to the API)
+/// has the same value in `actual` (the value returned by the API), which
+/// carries extra fields such as IDs, annotations and plain text
+pub fn property_value_matches(expected: &Value, actual: &Value) -> bool {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => expected.iter().all(|(key, value)| {
+            actual.get(key).map_or(value.is_null(), |actual| {
+                property_value_matches(value, actual)
+            })
+        }),
+        (Value::Array(expected), Value::Array(actual)) => {
+            expected.len() == actual.len()
+                && expected
+                    .iter()
+                    .zip(actual)
+                    .all(|(expected, actual)| property_value_matches(expected, actual))
+        }
+        (Value::Number(expected), Value::Number(actual)) => expected.as_f64() == actual.as_f64(),
+        _ => expected == actual,
+    }
+}
+
+/// Filter matching pages whose key property equals `key_value`, and the
+/// property value setting the key on a new page (`None` for unique IDs,
+/// which Notion assigns)
+fn key_filter(
+    database: &Database,
+    key_property: &str,
+    key_value: &str,
+) -> Result<(Value, Option<Value>), NotionError> {
+    let invalid = |message: String| NotionError::InvalidProperty {
+        property: key_property.to_string(),
+        message,
+    };
+    let property = database.properties.get(key_property).ok_or_else(|| {
+        NotionError::InvalidRequest(format!("Unknown property \"{}\"", key_property))
+    })?;
+
+    let number = |text: &str| {
+        text.trim()
+            .parse::<f64>()
+            .map_err(|_| invalid(format!("\"{}\" is not a number", key_value)))
+    };
+    match &property.schema {
+        PropertySchema::Title | PropertySchema::RichText => {
+            let kind = property.schema.type_name();
+            Ok((
+                json!({ "property": key_property, kind: { "equals": key_value } }),
+                Some(json!({ kind: text_objects(key_value) })),
+            ))
+        }
+        PropertySchema::Number(_) => {
+            let number = number(key_value)?;
+            Ok((
+                json!({ "property": key_property, "number": { "equals": number } }),
+                Some(json!({ "number": number })),
+            ))
+        }
+        PropertySchema::UniqueId(config) => {
+            // Accept both "TASK-42" and "42"
+            let digits = match &config.prefix {
+                Some(prefix) => key_value
+                    .strip_prefix(prefix.as_str())
+                    .and_then(|rest| rest.strip_prefix('-'))
+                    .unwrap_or(key_value),
+                None => key_value,
+            };
+            let number = number(digits)?;
+            Ok((
+                json!({ "property": key_property, "unique_id": { "equals": number } }),
+                None,
+            ))
+        }
+        other => Err(invalid(format!(
+            "{} properties cannot be used as upsert keys",
+            other.type_name()
+        ))),
+    }
+}
+
+impl NotionClient {
+    /// Updates the page of a database whose `key_property` equals
+    /// `key_value`, or creates it when there is none.
+    ///
+    /// The key may be a title, rich text, number or unique ID property. Only
+    /// properties whose values differ from the existing page are sent. New
+    /// pages get the key value unless the key is a unique ID, which Notion
+    /// assigns itself. Fails without writing anything when several pages have
+    /// the key.
+    pub async fn upsert_page(
+        &self,
+        database_id: &str,
+        key_property: &str,
+        key_value: &str,
+        properties: Value,
+    ) -> Result<UpsertResult, NotionError> {
+        let database = self.get_database(database_id).await?.data;
+        self.upsert_in(&database, key_property, key_value, properties)
+            .await
+    }
+
+    /// Upserts several `(key_value, properties)` records one after another,
+    /// fetching the database schema once. Returns one result per record, in
+    /// order; a failed record does not stop the others.
+    pub async fn upsert_pages(
+        &self,
+        database_id: &str,
+        key_property: &str,
+        records: Vec<(String, Value)>,
+    ) -> Result<Vec<Result<UpsertResult, NotionError>>, NotionError> {
+        let database = self.get_database(database_id).await?.data;
+        let mut results = Vec::with_capacity(records.len());
+        for (key_value, properties) in records {
+            results.push(
+                self.upsert_in(&database, key_property, &key_value, properties)
+                    .await,
+            );
+        }
+        Ok(results)
+    }
+
+    async fn upsert_in(
+        &self,
+        database: &Database,
+        key_property: &str,
+        key_value: &str,
+        properties: Value,
+    ) -> Result<UpsertResult, NotionError> {
+        let Value::Object(mut properties) = properties else {
+            return Err(NotionError::InvalidRequest(
+                "Properties must be a JSON object".to_string(),
+            ));
+        };
+        let (filter, key) = key_filter(database, key_property, key_value)?;
+        let existing = self
+            .query_database(
+                &database.id,
+                DatabaseQuery::new().filter(filter).page_size(2),
+            )
+            .await?
+            .results;
+
+        match existing.as_slice() {
+            [] => {
+                if let Some(key) = key {
+                    properties.entry(key_property).or_insert(key);
+                }
+                let created: ObjectResponse<Page> = self
+                    .post(Page::create_in_database_request(
+                        &database.id,
+                        Value::Object(properties),
+                    ))
+                    .await?;
+                Ok(UpsertResult {
+                    outcome: UpsertOutcome::Created,
+                    page: created.data,
+                })
+            }
+            [page] => {
+                let changed: Map<String, Value> = properties
+                    .into_iter()
+                    .filter(|(name, value)| !property_value_matches(value, &page.properties[name]))
+                    .collect();
+                if changed.is_empty() {
+                    return Ok(UpsertResult {
+                        outcome: UpsertOutcome::Unchanged,
+                        page: page.clone(),
+                    });
+                }
+                let updated: ObjectResponse<Page> = self
+                    .patch(Page::update_properties_request(
+                        &page.id,
+                        Value::Object(changed),
+                    ))
+                    .await?;
+                Ok(UpsertResult {
+                    outcome: UpsertOutcome::Updated,
+                    page: updated.data,
+                })
+            }
+            _ => Err(NotionError::InvalidRequest(format!(
+                "Several pages have \"{}\" equal to \"{}\"",
+                key_property, key_value
+            ))),
+        }
+    }
+}
@@ -1,6 +1,8 @@
+#[cfg(feature = "integration")]
 use dotenvy::dotenv;
 use notion_rs::client::NotionClient;
-use notion_rs::database::DatabaseQuery;
+use notion_rs::database::{DatabaseQuery, Sort, SortDirection};
+use notion_rs::error::NotionError;
 
 #[tokio::test]
 async fn test_client_creation() {
@@ -16,6 +18,44 @@ async fn test_client_with_retry_config() {
     assert!(client.list_databases().await.is_err());
 }
 
+#[tokio::test]
+async fn test_query_rejects_oversized_page_size() {
+    let client = NotionClient::new("dummy_token");
+    let query = DatabaseQuery::new().page_size(101);
+    // Validation happens before any request is sent
+    let result = client.query_database("dummy_database", query).await;
+    assert!(matches!(result, Err(NotionError::InvalidRequest(_))));
+}
+
+#[test]
+fn test_query_builder() {
+    let query = DatabaseQuery::new()
+        .filter(serde_json::json!({ "property": "Done", "checkbox": { "equals": false } }))
+        .sort(Sort::ascending("Name"))
+        .sort(Sort::created_time(SortDirection::Descending))
+        .page_size(10);
+    assert_eq!(
+        serde_json::to_value(&query).unwrap(),
+        serde_json::json!({
+            "filter": { "property": "Done", "checkbox": { "equals": false } },
+            "sorts": [
+                { "property": "Name", "direction": "ascending" },
+                { "timestamp": "created_time", "direction": "descending" }
+            ],
+            "page_size": 10
+        })
+    );
+    assert!(query.validate().is_ok());
+}
+
+#[test]
+fn test_query_builder_defaults() {
+    let query = DatabaseQuery::new();
+    assert_eq!(serde_json::to_value(&query).unwrap(), serde_json::json!({}));
+    assert!(DatabaseQuery::new().page_size(100).validate().is_ok());
+    assert!(DatabaseQuery::new().page_size(0).validate().is_err());
+}
+
 // Integration tests using real Notion API token
 #[cfg(feature = "integration")]
 mod integration_tests {
@@ -103,6 +143,7 @@ mod integration_tests {
             sorts: None,
             page_size: Some(10),
             start_cursor: None,
+            filter_properties: Vec::new(),
         };
 
         let query_result = client.query_database(&database_id, query).await;